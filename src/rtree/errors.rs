@@ -0,0 +1,34 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::fmt::Result as FmtResult;
+
+use crate::geometries::GeometryType;
+
+#[derive(PartialEq, Eq)]
+#[derive(Debug)]
+pub enum RtreeError {
+    EmptyGeometry,
+    TooFewPoints { kind: GeometryType, required: usize, found: usize },
+    OpenRing,
+    DuplicateId(String),
+    UnknownId(String)
+}
+
+impl Display for RtreeError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::EmptyGeometry => f.write_str("geometry has no coordinates"),
+            Self::TooFewPoints { kind, required, found } => f.write_fmt(format_args!(
+                "{} needs at least {} points, got {}",
+                kind,
+                required,
+                found
+            )),
+            Self::OpenRing => f.write_str("polygon ring is not closed"),
+            Self::DuplicateId(id) => f.write_fmt(format_args!("id {} is already in the tree", id)),
+            Self::UnknownId(id) => f.write_fmt(format_args!("no geometry with id {}", id))
+        }
+    }
+}
+
+impl Error for RtreeError {}
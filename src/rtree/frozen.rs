@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::{Coordinates, Scalar};
+use crate::geometries::{BoundingRectangle, RtreeGeometry};
+use crate::nodes::{ChildrenType, RtreeNode, TreeGeometry, TreeNode};
+
+enum FrozenChildren {
+    Nodes(Range<usize>),
+    Geometries(Range<usize>)
+}
+
+struct FrozenNode<S: Scalar> {
+    id: String,
+    mbr: BoundingRectangle<S>,
+    children: FrozenChildren
+}
+
+pub struct FrozenRtree<S: Scalar = i64> {
+    nodes: Vec<FrozenNode<S>>,
+    mbrs: Vec<BoundingRectangle<S>>,
+    geometries: Vec<RtreeGeometry<S>>
+}
+
+impl<S: Scalar> FrozenRtree<S> {
+    pub(crate) fn from_root(root: RtreeNode<S>) -> FrozenRtree<S> {
+        let mut frozen = FrozenRtree { nodes: vec!(), mbrs: vec!(), geometries: vec!() };
+        frozen.nodes.push(FrozenNode {
+            id: root.id.clone(),
+            mbr: root.mbr,
+            children: FrozenChildren::Nodes(0..0)
+        });
+
+        let mut queue: VecDeque<(usize, TreeNode<S>)> = VecDeque::new();
+        let mut leafs: Vec<TreeGeometry<S>> = vec!();
+        frozen.layout(0, &root, &mut queue, &mut leafs);
+        while let Some((index, node)) = queue.pop_front() {
+            frozen.layout(index, &node.borrow(), &mut queue, &mut leafs);
+        }
+        drop(root);
+        frozen.geometries = leafs.into_iter()
+            .map(|leaf| match Rc::try_unwrap(leaf) {
+                Ok(leaf) => leaf.into_inner(),
+                Err(leaf) => leaf.borrow().clone()
+            })
+            .collect();
+        frozen
+    }
+
+    fn layout(&mut self, index: usize, node: &RtreeNode<S>,
+              queue: &mut VecDeque<(usize, TreeNode<S>)>, leafs: &mut Vec<TreeGeometry<S>>) {
+        self.nodes[index].children = match &node.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let start = self.nodes.len();
+                for child in nodes {
+                    queue.push_back((self.nodes.len(), child.clone()));
+                    let child = child.borrow();
+                    self.nodes.push(FrozenNode {
+                        id: child.id.clone(),
+                        mbr: child.mbr,
+                        children: FrozenChildren::Nodes(0..0)
+                    });
+                }
+                FrozenChildren::Nodes(start..self.nodes.len())
+            },
+            ChildrenType::Leafs(children) => {
+                let start = leafs.len();
+                for leaf in children {
+                    self.mbrs.push(leaf.borrow().mbr);
+                    leafs.push(leaf.clone());
+                }
+                FrozenChildren::Geometries(start..leafs.len())
+            }
+        };
+    }
+
+    pub fn root_id(&self) -> &str {
+        &self.nodes[0].id
+    }
+
+    pub fn len(&self) -> usize {
+        self.geometries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.geometries.is_empty()
+    }
+
+    pub fn geometries(&self) -> &[RtreeGeometry<S>] {
+        &self.geometries
+    }
+
+    pub fn query_window(&self, query: &BoundingRectangle<S>) -> Vec<&RtreeGeometry<S>> {
+        let mut found = vec!();
+        let mut stack = vec!(0);
+        while let Some(index) = stack.pop() {
+            match &self.nodes[index].children {
+                FrozenChildren::Nodes(range) => {
+                    for child in range.clone() {
+                        if self.nodes[child].mbr.intersects(query) {
+                            stack.push(child)
+                        }
+                    }
+                },
+                FrozenChildren::Geometries(range) => {
+                    for geom_index in range.clone() {
+                        if self.mbrs[geom_index].intersects(query) {
+                            found.push(&self.geometries[geom_index])
+                        }
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    pub fn nearest_k(&self, point: Coordinates<S>, k: usize) -> Vec<(&RtreeGeometry<S>, f64)> {
+        let mut best: Vec<(f64, usize)> = vec!();
+        if k > 0 {
+            self.search_nearest(0, point, k, &mut best);
+        }
+        best.into_iter().map(|(distance, index)| (&self.geometries[index], distance)).collect()
+    }
+
+    fn search_nearest(&self, index: usize, point: Coordinates<S>, k: usize,
+                      best: &mut Vec<(f64, usize)>) {
+        match &self.nodes[index].children {
+            FrozenChildren::Nodes(range) => {
+                let mut ordered: Vec<(f64, usize)> = range.clone()
+                    .map(|child| (self.nodes[child].mbr.min_dist(point), child))
+                    .collect();
+                ordered.sort_by(|a, b| a.0.total_cmp(&b.0));
+                for (distance, child) in ordered {
+                    if best.len() == k && distance > best[k - 1].0 {
+                        break
+                    }
+                    self.search_nearest(child, point, k, best);
+                }
+            },
+            FrozenChildren::Geometries(range) => {
+                for geom_index in range.clone() {
+                    let distance = self.mbrs[geom_index].min_dist(point);
+                    if best.len() == k && distance >= best[k - 1].0 {
+                        continue
+                    }
+                    let position = best.partition_point(|entry| entry.0 <= distance);
+                    best.insert(position, (distance, geom_index));
+                    best.truncate(k);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::grid_tree;
+    use std::time::Instant;
+
+    #[test]
+    fn test_freeze() {
+        let root = grid_tree(15);
+        let query = BoundingRectangle::new((7, 5), (25, 19));
+        let mut expected: Vec<String> = root.query_window(&query).iter()
+            .map(|geom| geom.borrow().id.clone())
+            .collect();
+        let expected_nearest: Vec<String> = root.nearest_k((20, 20), 5).iter()
+            .map(|(geom, _)| geom.borrow().id.clone())
+            .collect();
+        let root_id = root.id.clone();
+
+        let frozen = root.freeze();
+        let mut found: Vec<String> = frozen.query_window(&query).iter()
+            .map(|geom| geom.id.clone())
+            .collect();
+        let nearest: Vec<String> = frozen.nearest_k((20, 20), 5).iter()
+            .map(|(geom, _)| geom.id.clone())
+            .collect();
+        expected.sort();
+        found.sort();
+
+        assert_eq!(frozen.len(), 225);
+        assert_eq!(frozen.root_id(), root_id);
+        assert!(!found.is_empty());
+        assert_eq!(found, expected);
+        assert_eq!(nearest, expected_nearest);
+    }
+
+    #[test]
+    fn test_freeze_shared_geometry() {
+        let root = grid_tree(4);
+        let shared = root.geometries()[3].clone();
+        let id = shared.borrow().id.clone();
+        let mbr = shared.borrow().mbr;
+
+        let frozen = root.freeze();
+        shared.borrow_mut().set_coords(vec!((100, 100))).unwrap();
+        let frozen_geom = frozen.geometries().iter().find(|geom| geom.id == id).unwrap();
+
+        assert_eq!(frozen.len(), 16);
+        assert_eq!((frozen_geom.mbr.left, frozen_geom.mbr.right), (mbr.left, mbr.right));
+        assert!(frozen.geometries().iter().all(|geom| geom.parent_node().is_none()));
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_frozen_query_window() {
+        let root = grid_tree(200);
+        let queries: Vec<BoundingRectangle> = (0..400)
+            .map(|i| BoundingRectangle::from_center(((i % 20) * 30 + 22, (i / 20) * 30 + 22), 22, 22))
+            .collect();
+
+        let start = Instant::now();
+        for _ in 0..20 {
+            for query in &queries {
+                root.query_window(query);
+            }
+        }
+        let shared = start.elapsed();
+
+        let frozen = root.freeze();
+        let start = Instant::now();
+        for _ in 0..20 {
+            for query in &queries {
+                frozen.query_window(query);
+            }
+        }
+        let flat = start.elapsed();
+
+        println!("shared: {:?}, frozen: {:?}", shared, flat);
+    }
+}
@@ -1,14 +1,14 @@
 use std::cell::RefCell;
-use std::cmp;
-use std::fmt::{Display, Debug, Formatter};
+use std::fmt::{Display, Formatter};
 use std::fmt::Result as FmtResult;
 use std::rc::{Rc, Weak};
 
-use crate::{INF, NEGINF, Coordinates, Geometry};
+use crate::{Coordinates, Geometry, Scalar};
+use crate::errors::RtreeError;
 use crate::nodes::{RtreeNode, RtreeObject, TreeGeometry, TreeNode};
-use crate::utils::generate_id;
+use crate::utils::{generate_id, partial_max, partial_min, IdSource};
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 #[derive(Debug)]
 pub enum GeometryType {
     Point,
@@ -17,18 +17,37 @@ pub enum GeometryType {
 }
 
 #[derive(Copy, Clone)]
-pub struct BoundingRectangle {
-    pub left: Coordinates,
-    pub right: Coordinates,
-    pub area: i64
+#[derive(PartialEq, Eq, Debug)]
+pub enum Boundary {
+    Inclusive,
+    Exclusive
 }
 
-pub struct RtreeGeometry {
+#[derive(Copy, Clone)]
+#[derive(PartialEq, Eq, Debug)]
+pub enum Corner {
+    BottomLeft,
+    BottomRight,
+    TopLeft,
+    TopRight
+}
+
+#[derive(Copy, Clone)]
+pub struct BoundingRectangle<S: Scalar = i64> {
+    pub left: Coordinates<S>,
+    pub right: Coordinates<S>,
+    pub area: S
+}
+
+#[derive(Clone)]
+pub struct RtreeGeometry<S: Scalar = i64> {
     pub id: String,
-    pub coords: Geometry,
-    pub mbr: BoundingRectangle,
+    pub coords: Geometry<S>,
+    pub mbr: BoundingRectangle<S>,
     pub coordtype: GeometryType,
-    parent: Option<Weak<RefCell<RtreeNode>>>
+    parent: Option<Weak<RefCell<RtreeNode<S>>>>,
+    sequence: u64,
+    typed: bool
 }
 
 impl Display for GeometryType {
@@ -41,7 +60,7 @@ impl Display for GeometryType {
     }
 }
 
-impl Display for BoundingRectangle {
+impl<S: Scalar> Display for BoundingRectangle<S> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         f.write_fmt(format_args!(
             "BL: ({}, {}), UR: ({}, {})",
@@ -53,96 +72,236 @@ impl Display for BoundingRectangle {
     }
 }
 
-impl BoundingRectangle {
+impl<S: Scalar> BoundingRectangle<S> {
 
-    pub fn new(left: Coordinates, right: Coordinates) -> BoundingRectangle {
+    pub fn new(left: Coordinates<S>, right: Coordinates<S>) -> BoundingRectangle<S> {
         BoundingRectangle {
-            left: left,
-            right: right,
+            left,
+            right,
             area: BoundingRectangle::count_area(&left, &right)
         }
     }
 
-    fn count_area(left: &Coordinates, right: &Coordinates) -> i64 {
-        (right.0 - left.0) * (right.1 - left.1)
+    pub fn from_center(center: Coordinates<S>, half_width: S,
+                       half_height: S) -> BoundingRectangle<S> {
+        BoundingRectangle::new(
+            (center.0 - half_width, center.1 - half_height),
+            (center.0 + half_width, center.1 + half_height)
+        )
+    }
+
+    fn count_area(left: &Coordinates<S>, right: &Coordinates<S>) -> S {
+        S::area(*left, *right)
+    }
+
+    fn empty() -> BoundingRectangle<S> {
+        BoundingRectangle {
+            left: (S::max_value(), S::max_value()),
+            right: (S::min_value(), S::min_value()),
+            area: S::zero()
+        }
+    }
+
+    pub fn width(&self) -> S {
+        self.right.0.saturating_sub(self.left.0)
+    }
+
+    pub fn height(&self) -> S {
+        self.right.1.saturating_sub(self.left.1)
+    }
+
+    pub fn min_x(&self) -> S {
+        self.left.0
     }
 
-    pub fn generate_mbr(coords: &Geometry) -> BoundingRectangle {
-        let mut min_x = INF;
-        let mut min_y = INF;
-        let mut max_x = NEGINF;
-        let mut max_y = NEGINF;
+    pub fn min_y(&self) -> S {
+        self.left.1
+    }
 
-        for coord in coords {
-            let x = coord.0;
-            let y = coord.1;
+    pub fn max_x(&self) -> S {
+        self.right.0
+    }
 
-            if x < min_x {
-                min_x = x
-            } else if x > max_x {
-                max_x = x
-            };
+    pub fn max_y(&self) -> S {
+        self.right.1
+    }
+
+    pub fn generate_mbr(coords: &Geometry<S>) -> BoundingRectangle<S> {
+        let first = match coords.first() {
+            Some(first) => *first,
+            None => return BoundingRectangle::empty()
+        };
+        let (mut min_x, mut min_y) = first;
+        let (mut max_x, mut max_y) = first;
 
-            if y < min_y {
-                min_y = y
-            } else if y > max_y {
-                max_y = y
-            };
+        for coord in &coords[1..] {
+            min_x = partial_min(min_x, coord.0);
+            min_y = partial_min(min_y, coord.1);
+            max_x = partial_max(max_x, coord.0);
+            max_y = partial_max(max_y, coord.1);
         }
         BoundingRectangle::new((min_x, min_y), (max_x, max_y))
     }
 
-    pub fn overlap_rectangle(rect_1: &BoundingRectangle,
-                            rect_2: &BoundingRectangle) -> BoundingRectangle {
+    pub fn overlap_rectangle(rect_1: &BoundingRectangle<S>,
+                            rect_2: &BoundingRectangle<S>) -> BoundingRectangle<S> {
         let left = (
-            cmp::max(rect_1.left.0, rect_2.left.0),
-            cmp::max(rect_1.left.1, rect_2.left.1)
+            partial_max(rect_1.left.0, rect_2.left.0),
+            partial_max(rect_1.left.1, rect_2.left.1)
         );
         let right = (
-            cmp::min(rect_1.right.0, rect_2.right.0),
-            cmp::min(rect_1.right.1, rect_2.right.1)
+            partial_min(rect_1.right.0, rect_2.right.0),
+            partial_min(rect_1.right.1, rect_2.right.1)
         );
         BoundingRectangle::new(left, right)
     }
 
-    pub fn common_mbr(list_mbrs: &Vec<&BoundingRectangle>) -> BoundingRectangle {
-        let mut min_x = INF;
-        let mut min_y = INF;
-        let mut max_x = NEGINF;
-        let mut max_y = NEGINF;
-
-        for mbr in list_mbrs {
-            if mbr.left.0 < min_x {
-                min_x = mbr.left.0
-            };
-            if mbr.right.0 > max_x {
-                max_x = mbr.right.0
-            };
-            if mbr.left.1 < min_y {
-                min_y = mbr.left.1
-            }
-            if mbr.right.1 > max_y {
-                max_y = mbr.right.1
-            };
+    pub fn overlap_area(&self, rectangle: &BoundingRectangle<S>) -> S {
+        match self.intersection(rectangle) {
+            Some(overlap) => overlap.area,
+            None => S::zero()
         }
+    }
 
-        BoundingRectangle::new((min_x, min_y), (max_x, max_y))
+    pub fn union(&self, rectangle: &BoundingRectangle<S>) -> BoundingRectangle<S> {
+        BoundingRectangle::new(
+            (partial_min(self.left.0, rectangle.left.0), partial_min(self.left.1, rectangle.left.1)),
+            (partial_max(self.right.0, rectangle.right.0), partial_max(self.right.1, rectangle.right.1))
+        )
     }
 
-    pub fn intersects(&self, rectangle: &BoundingRectangle) -> bool {
-        if (self.left.0 > rectangle.right.0) ||
-                (rectangle.left.0 > self.right.0) {
-            false
-        } else if (self.right.1 < rectangle.left.1) ||
-                (rectangle.right.1 < self.left.1) {
-            false
+    pub fn intersection(&self, rectangle: &BoundingRectangle<S>) -> Option<BoundingRectangle<S>> {
+        if self.intersects(rectangle) {
+            Some(BoundingRectangle::overlap_rectangle(self, rectangle))
         } else {
-            true
+            None
+        }
+    }
+
+    pub fn common_mbr(list_mbrs: &Vec<&BoundingRectangle<S>>) -> BoundingRectangle<S> {
+        let first = match list_mbrs.first() {
+            Some(first) => first,
+            None => return BoundingRectangle::empty()
+        };
+        let (mut min_x, mut min_y) = first.left;
+        let (mut max_x, mut max_y) = first.right;
+
+        for mbr in &list_mbrs[1..] {
+            min_x = partial_min(min_x, mbr.left.0);
+            min_y = partial_min(min_y, mbr.left.1);
+            max_x = partial_max(max_x, mbr.right.0);
+            max_y = partial_max(max_y, mbr.right.1);
+        }
+
+        BoundingRectangle::new((min_x, min_y), (max_x, max_y))
+    }
+
+    /// An empty slice gives an inverted box (left at `S::max_value()`, right at
+    /// `S::min_value()`) with zero area, which intersects and contains nothing.
+    pub fn of_geometries(geoms: &[&RtreeGeometry<S>]) -> BoundingRectangle<S> {
+        BoundingRectangle::common_mbr(&geoms.iter().map(|geom| &geom.mbr).collect())
+    }
+
+    pub fn center(&self) -> Coordinates<S> {
+        (self.left.0.midpoint(self.right.0), self.left.1.midpoint(self.right.1))
+    }
+
+    pub fn center_f64(&self) -> (f64, f64) {
+        (
+            (self.left.0.to_f64() + self.right.0.to_f64()) / 2.0,
+            (self.left.1.to_f64() + self.right.1.to_f64()) / 2.0
+        )
+    }
+
+    pub fn to_square(&self) -> BoundingRectangle<S> {
+        let two = S::one() + S::one();
+        let side = partial_max(self.width(), self.height());
+        let left = (
+            self.left.0 - (side - self.width()) / two,
+            self.left.1 - (side - self.height()) / two
+        );
+        BoundingRectangle::new(left, (left.0 + side, left.1 + side))
+    }
+
+    pub fn buffer(&self, amount: S) -> BoundingRectangle<S> {
+        let center = self.center();
+        let mut left = (self.left.0 - amount, self.left.1 - amount);
+        let mut right = (self.right.0 + amount, self.right.1 + amount);
+        if left.0 > right.0 {
+            left.0 = center.0;
+            right.0 = center.0;
+        }
+        if left.1 > right.1 {
+            left.1 = center.1;
+            right.1 = center.1;
+        }
+        BoundingRectangle::new(left, right)
+    }
+
+    pub fn min_dist(&self, point: Coordinates<S>) -> f64 {
+        let (x, y) = (point.0.to_f64(), point.1.to_f64());
+        let dx = (self.left.0.to_f64() - x).max(x - self.right.0.to_f64()).max(0.0);
+        let dy = (self.left.1.to_f64() - y).max(y - self.right.1.to_f64()).max(0.0);
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    pub fn min_dist_rect(&self, rectangle: &BoundingRectangle<S>) -> f64 {
+        let dx = (rectangle.left.0.to_f64() - self.right.0.to_f64())
+            .max(self.left.0.to_f64() - rectangle.right.0.to_f64())
+            .max(0.0);
+        let dy = (rectangle.left.1.to_f64() - self.right.1.to_f64())
+            .max(self.left.1.to_f64() - rectangle.right.1.to_f64())
+            .max(0.0);
+        (dx * dx + dy * dy).sqrt()
+    }
+
+    pub fn intersects(&self, rectangle: &BoundingRectangle<S>) -> bool {
+        !((self.left.0 > rectangle.right.0) ||
+                (rectangle.left.0 > self.right.0) ||
+                (self.right.1 < rectangle.left.1) ||
+                (rectangle.right.1 < self.left.1))
+    }
+
+    pub fn contains(&self, rectangle: &BoundingRectangle<S>) -> bool {
+        self.left.0 <= rectangle.left.0 && self.left.1 <= rectangle.left.1 &&
+            self.right.0 >= rectangle.right.0 && self.right.1 >= rectangle.right.1
+    }
+
+    pub fn intersects_convex(&self, corners: &[(f64, f64)]) -> bool {
+        let rect_corners = [
+            (self.left.0.to_f64(), self.left.1.to_f64()),
+            (self.right.0.to_f64(), self.left.1.to_f64()),
+            (self.right.0.to_f64(), self.right.1.to_f64()),
+            (self.left.0.to_f64(), self.right.1.to_f64())
+        ];
+        let mut axes = vec!((1.0, 0.0), (0.0, 1.0));
+        for index in 0..corners.len() {
+            let start = corners[index];
+            let end = corners[(index + 1) % corners.len()];
+            axes.push((start.1 - end.1, end.0 - start.0));
+        }
+
+        axes.iter().all(|axis| {
+            let (rect_min, rect_max) = project(&rect_corners, *axis);
+            let (poly_min, poly_max) = project(corners, *axis);
+            rect_min <= poly_max && poly_min <= rect_max
+        })
+    }
+
+    pub fn intersects_with(&self, rectangle: &BoundingRectangle<S>, boundary: Boundary) -> bool {
+        match boundary {
+            Boundary::Inclusive => self.intersects(rectangle),
+            Boundary::Exclusive => {
+                !((self.left.0 >= rectangle.right.0) ||
+                        (rectangle.left.0 >= self.right.0) ||
+                        (self.right.1 <= rectangle.left.1) ||
+                        (rectangle.right.1 <= self.left.1))
+            }
         }
     }
 }
 
-impl Display for RtreeGeometry {
+impl<S: Scalar> Display for RtreeGeometry<S> {
     fn fmt(&self, f: &mut Formatter) -> FmtResult {
         f.write_fmt(format_args!(
             "{} with mbr {}",
@@ -152,57 +311,269 @@ impl Display for RtreeGeometry {
     }
 }
 
-impl RtreeGeometry {
+impl<S: Scalar> RtreeGeometry<S> {
 
-    pub fn new(coords: Geometry) -> RtreeGeometry {
+    pub fn new(coords: Geometry<S>) -> RtreeGeometry<S> {
+        let coordtype = RtreeGeometry::infer_type(&coords);
+        let mbr = RtreeGeometry::find_mbr(&coordtype, &coords);
+        RtreeGeometry {
+            id: generate_id(&IdSource::Uuid),
+            coords,
+            mbr,
+            coordtype,
+            parent: None,
+            sequence: 0,
+            typed: false
+        }
+    }
+
+    pub fn point(coord: Coordinates<S>) -> RtreeGeometry<S> {
+        RtreeGeometry {
+            id: generate_id(&IdSource::Uuid),
+            coords: vec!(coord),
+            mbr: BoundingRectangle::new(coord, coord),
+            coordtype: GeometryType::Point,
+            parent: None,
+            sequence: 0,
+            typed: false
+        }
+    }
+
+    pub fn with_type(coords: Geometry<S>,
+                     kind: GeometryType) -> Result<RtreeGeometry<S>, RtreeError> {
+        RtreeGeometry::check_type(&coords, &kind)?;
+
+        let mbr = RtreeGeometry::find_mbr(&kind, &coords);
+        Ok(RtreeGeometry {
+            id: generate_id(&IdSource::Uuid),
+            coords,
+            mbr,
+            coordtype: kind,
+            parent: None,
+            sequence: 0,
+            typed: true
+        })
+    }
+
+    fn check_type(coords: &Geometry<S>, kind: &GeometryType) -> Result<(), RtreeError> {
+        let required = match kind {
+            GeometryType::Point => 1,
+            GeometryType::Line => 2,
+            GeometryType::Polygon => 3
+        };
+        if coords.is_empty() {
+            return Err(RtreeError::EmptyGeometry)
+        }
+        if coords.len() < required {
+            return Err(RtreeError::TooFewPoints { kind: kind.clone(), required, found: coords.len() })
+        }
+        if *kind == GeometryType::Polygon && coords[0] != coords[coords.len() - 1] {
+            return Err(RtreeError::OpenRing)
+        }
+        Ok(())
+    }
+
+    pub fn new_shared(coords: Geometry<S>) -> TreeGeometry<S> {
+        Rc::new(RefCell::new(RtreeGeometry::new(coords)))
+    }
+
+    pub fn coords(&self) -> &Geometry<S> {
+        &self.coords
+    }
+
+    pub fn geometry_type(&self) -> &GeometryType {
+        &self.coordtype
+    }
+
+    pub fn parent_node(&self) -> Option<TreeNode<S>> {
+        self.parent.as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    pub(crate) fn set_sequence(&mut self, sequence: u64) {
+        self.sequence = sequence
+    }
+
+    pub fn set_coords(&mut self, coords: Geometry<S>) -> Result<(), RtreeError> {
+        if self.typed {
+            RtreeGeometry::check_type(&coords, &self.coordtype)?;
+        } else if coords.is_empty() {
+            return Err(RtreeError::EmptyGeometry)
+        } else {
+            self.coordtype = RtreeGeometry::infer_type(&coords);
+        }
+        self.mbr = RtreeGeometry::find_mbr(&self.coordtype, &coords);
+        self.coords = coords;
+        Ok(())
+    }
+
+    pub fn snap_to(&mut self, precision: f64) {
+        assert!(precision.is_finite() && precision > 0.0, "snap precision must be finite and positive");
+        let snap = |value: S| S::from_f64((value.to_f64() / precision).round() * precision);
+        let mut coords: Geometry<S> = self.coords.iter()
+            .map(|coord| (snap(coord.0), snap(coord.1)))
+            .collect();
+        coords.dedup();
+        self.mbr = RtreeGeometry::find_mbr(&self.coordtype, &coords);
+        self.coords = coords;
+    }
+
+    pub fn distance_to(&self, other: &RtreeGeometry<S>) -> f64 {
+        let own_points = self.points();
+        let other_points = other.points();
+        if (self.coordtype == GeometryType::Polygon && contains_point(&own_points, other_points[0])) ||
+            (other.coordtype == GeometryType::Polygon && contains_point(&other_points, own_points[0])) {
+            return 0.0
+        }
+
+        let mut distance = f64::INFINITY;
+        for own in segments(&own_points) {
+            for other in segments(&other_points) {
+                distance = distance.min(segment_distance(own, other));
+            }
+        }
+        distance
+    }
+
+    pub fn to_wkt(&self) -> String {
+        let points: Vec<String> = self.coords.iter()
+            .map(|coord| format!("{} {}", coord.0, coord.1))
+            .collect();
+        match self.coordtype {
+            GeometryType::Point => format!("POINT ({})", points[0]),
+            GeometryType::Line => format!("LINESTRING ({})", points.join(", ")),
+            GeometryType::Polygon => format!("POLYGON (({}))", points.join(", "))
+        }
+    }
+
+    pub fn intersects_rect(&self, rectangle: &BoundingRectangle<S>) -> bool {
+        if !self.mbr.intersects(rectangle) {
+            return false
+        }
+        let points = self.points();
+        let crosses = segments(&points).iter()
+            .any(|segment| rectangle.intersects_convex(&[segment.0, segment.1]));
+        if crosses || self.coordtype != GeometryType::Polygon {
+            return crosses
+        }
+        contains_point(&points, (rectangle.left.0.to_f64(), rectangle.left.1.to_f64()))
+    }
+
+    fn points(&self) -> Vec<(f64, f64)> {
+        self.coords.iter().map(|coord| (coord.0.to_f64(), coord.1.to_f64())).collect()
+    }
+
+    pub(crate) fn infer_type(coords: &Geometry<S>) -> GeometryType {
         let length = coords.len();
-        let coordtype = if length < 2 {
+        if length < 2 {
             GeometryType::Point
         } else if coords[0] == coords[length - 1] {
             GeometryType::Polygon
         } else {
             GeometryType::Line
-        };
-        
-        let mbr = RtreeGeometry::find_mbr(&coordtype, &coords);
-        RtreeGeometry {
-            id: generate_id(),
-            coords,
-            mbr,
-            coordtype,
-            parent: None
         }
     }
 
-    fn find_mbr(coordtype: &GeometryType, coords: &Geometry) -> BoundingRectangle {
+    pub(crate) fn find_mbr(coordtype: &GeometryType, coords: &Geometry<S>) -> BoundingRectangle<S> {
         match *coordtype {
             GeometryType::Point => BoundingRectangle::new(coords[0], coords[0]),
             _ => {
-                BoundingRectangle::generate_mbr(&coords)
+                BoundingRectangle::generate_mbr(coords)
             }
         }
     }
 }
 
-impl RtreeObject for RtreeGeometry {
+impl<S: Scalar> RtreeObject<S> for RtreeGeometry<S> {
 
     fn id(&self) -> &str {
         &self.id
     }
 
-    fn mbr(&self) -> &BoundingRectangle {
+    fn mbr(&self) -> &BoundingRectangle<S> {
         &self.mbr
     }
 
-    fn set_mbr(&mut self, mbr: BoundingRectangle) -> () {
+    fn set_mbr(&mut self, mbr: BoundingRectangle<S>) {
         self.mbr = mbr
     }
 
-    fn set_parent(&mut self, node: &TreeNode) -> () {
+    fn set_parent(&mut self, node: &TreeNode<S>) {
         self.parent = Some(Rc::downgrade(node))
     }
 }
 
+type Segment = ((f64, f64), (f64, f64));
+
+fn segments(points: &[(f64, f64)]) -> Vec<Segment> {
+    if points.len() == 1 {
+        return vec!((points[0], points[0]))
+    }
+    points.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+fn cross(origin: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - origin.0) * (b.1 - origin.1) - (a.1 - origin.1) * (b.0 - origin.0)
+}
+
+fn point_segment_distance(point: (f64, f64), segment: Segment) -> f64 {
+    let (start, end) = segment;
+    let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+    let length = dx * dx + dy * dy;
+    let t = if length == 0.0 {
+        0.0
+    } else {
+        (((point.0 - start.0) * dx + (point.1 - start.1) * dy) / length).clamp(0.0, 1.0)
+    };
+    let (x, y) = (start.0 + t * dx - point.0, start.1 + t * dy - point.1);
+    (x * x + y * y).sqrt()
+}
+
+fn segments_cross(a: Segment, b: Segment) -> bool {
+    let d1 = cross(b.0, b.1, a.0);
+    let d2 = cross(b.0, b.1, a.1);
+    let d3 = cross(a.0, a.1, b.0);
+    let d4 = cross(a.0, a.1, b.1);
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0)) &&
+        ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+fn segment_distance(a: Segment, b: Segment) -> f64 {
+    if segments_cross(a, b) {
+        return 0.0
+    }
+    point_segment_distance(a.0, b)
+        .min(point_segment_distance(a.1, b))
+        .min(point_segment_distance(b.0, a))
+        .min(point_segment_distance(b.1, a))
+}
+
+fn contains_point(ring: &[(f64, f64)], point: (f64, f64)) -> bool {
+    let mut inside = false;
+    for pair in ring.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if (a.1 > point.1) != (b.1 > point.1) &&
+            point.0 < a.0 + (point.1 - a.1) * (b.0 - a.0) / (b.1 - a.1) {
+            inside = !inside
+        }
+    }
+    inside
+}
+
+fn project(points: &[(f64, f64)], axis: (f64, f64)) -> (f64, f64) {
+    let mut min = f64::INFINITY;
+    let mut max = f64::NEG_INFINITY;
+    for point in points {
+        let value = point.0 * axis.0 + point.1 * axis.1;
+        min = min.min(value);
+        max = max.max(value);
+    }
+    (min, max)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -217,6 +588,41 @@ mod tests {
         assert_eq!(rect.area, 20);
     }
 
+    #[test]
+    fn test_float_mbr() {
+        let rect = BoundingRectangle::new((0.5, 1.0), (2.0, 3.5));
+        let common = BoundingRectangle::common_mbr(
+            &vec!(&rect, &BoundingRectangle::new((-1.0, 2.0), (1.0, 2.5)))
+        );
+
+        assert_eq!(rect.area, 3.75);
+        assert_eq!(rect.center(), (1.25, 2.25));
+        assert_eq!(common.left, (-1.0, 1.0));
+        assert_eq!(common.right, (2.0, 3.5));
+    }
+
+    #[test]
+    fn test_accessors() {
+        let rect = BoundingRectangle::new((2, -1), (7, 3));
+
+        assert_eq!(rect.width(), 5);
+        assert_eq!(rect.height(), 4);
+        assert_eq!(rect.min_x(), 2);
+        assert_eq!(rect.min_y(), -1);
+        assert_eq!(rect.max_x(), 7);
+        assert_eq!(rect.max_y(), 3);
+    }
+
+    #[test]
+    fn test_from_center() {
+        let rect = BoundingRectangle::from_center((10, -4), 3, 5);
+
+        assert_eq!(rect.left, (7, -9));
+        assert_eq!(rect.right, (13, 1));
+        assert_eq!(rect.area, 60);
+        assert_eq!(rect.center(), (10, -4));
+    }
+
     #[test]
     fn test_generate_mbr() {
         let coords_rect = &vec!((2, 2), (6, 2), (6, 4), (2, 4), (2, 2));
@@ -247,6 +653,45 @@ mod tests {
         assert_eq!(overlap.right, (5, 3));
     }
 
+    #[test]
+    fn test_overlap_area() {
+        let rect_1 = BoundingRectangle::new(
+            (2, 1), (5, 3)
+        );
+        let rect_2 = BoundingRectangle::new(
+            (4, 2), (7, 4)
+        );
+        let rect_3 = BoundingRectangle::new(
+            (6, 5), (8, 9)
+        );
+
+        assert_eq!(rect_1.overlap_area(&rect_2), 1);
+        assert_eq!(rect_2.overlap_area(&rect_1), 1);
+        assert_eq!(rect_1.overlap_area(&rect_3), 0);
+    }
+
+    #[test]
+    fn test_union_intersection() {
+        let rect_1 = BoundingRectangle::new(
+            (2, 1), (5, 3)
+        );
+        let rect_2 = BoundingRectangle::new(
+            (4, 2), (7, 4)
+        );
+        let rect_3 = BoundingRectangle::new(
+            (8, 6), (9, 9)
+        );
+
+        let union = rect_1.union(&rect_2);
+        let disjoint_union = rect_1.union(&rect_3);
+        let overlap = rect_1.intersection(&rect_2).unwrap();
+
+        assert_eq!((union.left, union.right), ((2, 1), (7, 4)));
+        assert_eq!((disjoint_union.left, disjoint_union.right), ((2, 1), (9, 9)));
+        assert_eq!((overlap.left, overlap.right), ((4, 2), (5, 3)));
+        assert!(rect_1.intersection(&rect_3).is_none());
+    }
+
     #[test]
     fn test_common_mbr() {
         let rect_1 = BoundingRectangle::new(
@@ -265,6 +710,17 @@ mod tests {
         assert_eq!(common.right, (7, 4));
     }
 
+    #[test]
+    fn test_min_dist_rect() {
+        let rect_1 = BoundingRectangle::new((2, 1), (5, 3));
+        let rect_2 = BoundingRectangle::new((4, 2), (7, 4));
+        let rect_3 = BoundingRectangle::new((8, 7), (9, 9));
+
+        assert_eq!(rect_1.min_dist_rect(&rect_2), 0.0);
+        assert_eq!(rect_1.min_dist_rect(&rect_3), 5.0);
+        assert_eq!(rect_3.min_dist_rect(&rect_1), 5.0);
+    }
+
     #[test]
     fn test_intersects() {
         let rect_1 = BoundingRectangle::new(
@@ -277,9 +733,73 @@ mod tests {
             (3, 0), (6, 1)
         );
 
-        assert_eq!(rect_1.intersects(&rect_2), true);
-        assert_eq!(rect_2.intersects(&rect_3), false);
-        assert_eq!(rect_1.intersects(&rect_3), true);
+        assert!(rect_1.intersects(&rect_2));
+        assert!(!rect_2.intersects(&rect_3));
+        assert!(rect_1.intersects(&rect_3));
+    }
+
+    #[test]
+    fn test_intersects_with() {
+        let rect_1 = BoundingRectangle::new(
+            (2, 1), (5, 3)
+        );
+        let rect_2 = BoundingRectangle::new(
+            (5, 2), (7, 4)
+        );
+        let rect_3 = BoundingRectangle::new(
+            (4, 2), (7, 4)
+        );
+
+        assert!(rect_1.intersects_with(&rect_2, Boundary::Inclusive));
+        assert!(!rect_1.intersects_with(&rect_2, Boundary::Exclusive));
+        assert!(rect_1.intersects_with(&rect_3, Boundary::Exclusive));
+    }
+
+    #[test]
+    fn test_contains() {
+        let outer = BoundingRectangle::new((0, 0), (10, 10));
+        let inner = BoundingRectangle::new((2, 3), (10, 5));
+        let crossing = BoundingRectangle::new((5, 5), (12, 8));
+
+        assert!(outer.contains(&inner));
+        assert!(outer.contains(&outer));
+        assert!(!inner.contains(&outer));
+        assert!(!outer.contains(&crossing));
+    }
+
+    #[test]
+    fn test_center() {
+        let rect = BoundingRectangle::new((2, 1), (6, 5));
+        let point = BoundingRectangle::new((3, 3), (3, 3));
+
+        assert_eq!(rect.center(), (4, 3));
+        assert_eq!(point.center(), (3, 3));
+        assert_eq!(BoundingRectangle::new((0, 0), (3, 1)).center_f64(), (1.5, 0.5));
+    }
+
+    #[test]
+    fn test_buffer() {
+        let rect = BoundingRectangle::new((2, 1), (6, 5));
+
+        let same = rect.buffer(0);
+        let grown = rect.buffer(3);
+        let shrunk = rect.buffer(-1);
+        let collapsed = rect.buffer(-10);
+
+        assert_eq!((same.left, same.right), (rect.left, rect.right));
+        assert_eq!((grown.left, grown.right), ((-1, -2), (9, 8)));
+        assert_eq!((shrunk.left, shrunk.right), ((3, 2), (5, 4)));
+        assert_eq!((collapsed.left, collapsed.right), ((4, 3), (4, 3)));
+        assert_eq!(collapsed.area, 0);
+    }
+
+    #[test]
+    fn test_min_dist() {
+        let rect = BoundingRectangle::new((2, 1), (5, 3));
+
+        assert_eq!(rect.min_dist((3, 2)), 0.0);
+        assert_eq!(rect.min_dist((5, 6)), 3.0);
+        assert_eq!(rect.min_dist((8, 7)), 5.0);
     }
 
     #[test]
@@ -304,4 +824,205 @@ mod tests {
 
         assert_eq!(point.mbr.left, point.mbr.right);
     }
+
+    #[test]
+    fn test_snap_to() {
+        let mut line = RtreeGeometry::new(vec!((0.12341, 5.0), (0.12338, 5.00002), (0.5, 5.0)));
+        let mut rough = RtreeGeometry::new(vec!((12, 3), (18, 7)));
+
+        line.snap_to(0.0001);
+        rough.snap_to(10.0);
+
+        assert_eq!(line.coords.len(), 2);
+        assert!((line.coords[0].0 - 0.1234).abs() < 1e-9);
+        assert_eq!(line.coords[0].1, 5.0);
+        assert_eq!(rough.coords, vec!((10, 0), (20, 10)));
+    }
+
+    #[test]
+    #[should_panic(expected = "snap precision must be finite and positive")]
+    fn test_snap_to_negative_precision() {
+        RtreeGeometry::new(vec!((15, 25))).snap_to(-1.0);
+    }
+
+    #[test]
+    fn test_with_type() {
+        let ring = vec!((2, 2), (6, 2), (6, 4), (2, 2));
+
+        let line = RtreeGeometry::with_type(ring.clone(), GeometryType::Line).unwrap();
+        let open = RtreeGeometry::with_type(vec!((2, 2), (6, 2), (6, 4)), GeometryType::Polygon);
+        let short = RtreeGeometry::with_type(vec!((2, 2)), GeometryType::Line);
+        let empty = RtreeGeometry::<i64>::with_type(vec!(), GeometryType::Point);
+
+        assert_eq!(RtreeGeometry::new(ring).coordtype, GeometryType::Polygon);
+        assert_eq!(line.coordtype, GeometryType::Line);
+        assert_eq!(line.mbr.left, (2, 2));
+        assert_eq!(line.mbr.right, (6, 4));
+        assert_eq!(open.err(), Some(RtreeError::OpenRing));
+        assert_eq!(
+            short.err(),
+            Some(RtreeError::TooFewPoints { kind: GeometryType::Line, required: 2, found: 1 })
+        );
+        assert_eq!(empty.err(), Some(RtreeError::EmptyGeometry));
+    }
+
+    #[test]
+    fn test_set_coords() {
+        let mut geom = RtreeGeometry::new(vec!((8, 6)));
+        let id = geom.id.clone();
+
+        assert_eq!(geom.set_coords(vec!((2, 2), (6, 2), (6, 4), (2, 2))), Ok(()));
+
+        assert_eq!(geom.id, id);
+        assert_eq!(geom.coordtype, GeometryType::Polygon);
+        assert_eq!(geom.mbr.left, (2, 2));
+        assert_eq!(geom.mbr.right, (6, 4));
+        assert_eq!(geom.set_coords(vec!()), Err(RtreeError::EmptyGeometry));
+    }
+
+    #[test]
+    fn test_type_override_survives_updates() {
+        let ring = vec!((2, 2), (6, 2), (6, 4), (2, 2));
+        let mut line = RtreeGeometry::with_type(ring.clone(), GeometryType::Line).unwrap();
+        let mut polygon = RtreeGeometry::with_type(ring, GeometryType::Polygon).unwrap();
+
+        line.snap_to(1.0);
+        assert_eq!(line.coordtype, GeometryType::Line);
+
+        assert_eq!(line.set_coords(vec!((0, 0), (3, 3), (0, 0))), Ok(()));
+        assert_eq!(line.coordtype, GeometryType::Line);
+        assert_eq!(polygon.set_coords(vec!((0, 0), (3, 3), (3, 0))), Err(RtreeError::OpenRing));
+        assert_eq!(polygon.coords, vec!((2, 2), (6, 2), (6, 4), (2, 2)));
+        assert_eq!(polygon.coordtype, GeometryType::Polygon);
+    }
+
+    #[test]
+    fn test_of_geometries() {
+        let geom_1 = RtreeGeometry::new(vec!((1, 2), (4, 6)));
+        let geom_2 = RtreeGeometry::new(vec!((-3, 0)));
+        let geom_3 = RtreeGeometry::new(vec!((2, 1), (7, 3), (5, 9), (2, 1)));
+
+        let mbr = BoundingRectangle::of_geometries(&[&geom_1, &geom_2, &geom_3]);
+        let expected = BoundingRectangle::common_mbr(&vec!(&geom_1.mbr, &geom_2.mbr, &geom_3.mbr));
+
+        assert_eq!(mbr.left, expected.left);
+        assert_eq!(mbr.right, expected.right);
+        assert_eq!(mbr.area, expected.area);
+
+        let single = BoundingRectangle::of_geometries(&[&geom_1]);
+        assert_eq!((single.left, single.right), (geom_1.mbr.left, geom_1.mbr.right));
+
+        let empty: BoundingRectangle = BoundingRectangle::of_geometries(&[]);
+        assert!(empty.left.0 > empty.right.0);
+        assert_eq!(empty.area, 0);
+        assert!(!empty.intersects(&geom_1.mbr));
+    }
+
+    #[test]
+    fn test_intersects_convex() {
+        let rect = BoundingRectangle::new((0, 0), (2, 2));
+        let diamond = [(4.0, 0.0), (6.0, 2.0), (4.0, 4.0), (2.0, 2.0)];
+        let touching = [(3.0, 1.0), (4.0, 2.0), (3.0, 3.0), (2.0, 2.0)];
+
+        assert!(!BoundingRectangle::new((0, 0), (1, 3)).intersects_convex(&diamond));
+        assert!(!BoundingRectangle::new((2, 0), (3, 0)).intersects_convex(&diamond));
+        assert!(rect.intersects_convex(&touching));
+        assert!(BoundingRectangle::new((3, 1), (5, 3)).intersects_convex(&diamond));
+    }
+
+    #[test]
+    fn test_distance_to() {
+        let line = RtreeGeometry::new(vec!((0, 0), (10, 0)));
+        let point = RtreeGeometry::new(vec!((5, 3)));
+        let crossing = RtreeGeometry::new(vec!((5, -2), (5, 2)));
+        let square = RtreeGeometry::new(vec!((0, 0), (20, 0), (20, 20), (0, 20), (0, 0)));
+        let inner = RtreeGeometry::new(vec!((8, 8), (9, 9)));
+
+        assert_eq!(line.distance_to(&point), 3.0);
+        assert_eq!(point.distance_to(&line), 3.0);
+        assert_eq!(line.distance_to(&crossing), 0.0);
+        assert_eq!(inner.distance_to(&square), 0.0);
+        assert_eq!(RtreeGeometry::new(vec!((0, 25))).distance_to(&square), 5.0);
+    }
+
+    #[test]
+    fn test_generate_mbr_unordered() {
+        let mbr = BoundingRectangle::generate_mbr(&vec!((12, 7), (18, 3), (4, 9)));
+        let extreme = BoundingRectangle::generate_mbr(&vec!((i64::MAX, i64::MIN), (i64::MAX, i64::MIN)));
+        let empty: BoundingRectangle = BoundingRectangle::common_mbr(&vec!());
+        let no_coords: BoundingRectangle = BoundingRectangle::generate_mbr(&vec!());
+        let huge = BoundingRectangle::new((i64::MIN, 0), (i64::MAX, i64::MAX));
+
+        assert_eq!((mbr.left, mbr.right), ((4, 3), (18, 9)));
+        assert_eq!(mbr.area, 84);
+        assert_eq!((extreme.left, extreme.right), ((i64::MAX, i64::MIN), (i64::MAX, i64::MIN)));
+        assert!(empty.left.0 > empty.right.0);
+        assert_eq!((no_coords.left, no_coords.right), (empty.left, empty.right));
+        assert_eq!(no_coords.area, 0);
+        assert_eq!(huge.area, i64::MAX);
+    }
+
+    #[test]
+    fn test_extreme_spans() {
+        let side: i64 = (1 << 30) + 1;
+        let exact = BoundingRectangle::new((0, 0), (side, side));
+        let wide = BoundingRectangle::new((i64::MIN, 0), (i64::MAX, 1));
+        let negative = BoundingRectangle::new((-3, -3), (0, 0));
+
+        assert_eq!(exact.area, 1152921506754330625);
+        assert_eq!(wide.area, i64::MAX);
+        assert_eq!(wide.width(), i64::MAX);
+        assert_eq!(wide.height(), 1);
+        assert_eq!(wide.center(), (-1, 0));
+        assert_eq!(negative.center(), (-2, -2));
+
+        let buffered = wide.buffer(0);
+        assert_eq!((buffered.left, buffered.right), (wide.left, wide.right));
+    }
+
+    #[test]
+    fn test_intersects_rect() {
+        let rect = BoundingRectangle::new((8, 8), (12, 12));
+        let diagonal = RtreeGeometry::new(vec!((0, 10), (10, 0)));
+        let crossing = RtreeGeometry::new(vec!((0, 20), (20, 0)));
+        let around = RtreeGeometry::new(vec!((0, 0), (30, 0), (30, 30), (0, 30), (0, 0)));
+        let notch = RtreeGeometry::new(vec!((0, 0), (20, 0), (0, 20), (0, 0)));
+
+        assert!(diagonal.mbr.intersects(&rect));
+        assert!(!diagonal.intersects_rect(&rect));
+        assert!(crossing.intersects_rect(&rect));
+        assert!(around.intersects_rect(&rect));
+        assert!(!notch.intersects_rect(&BoundingRectangle::new((11, 11), (15, 15))));
+        assert!(RtreeGeometry::new(vec!((9, 9))).intersects_rect(&rect));
+    }
+
+    #[test]
+    fn test_to_square() {
+        let wide = BoundingRectangle::new((0, 0), (10, 4));
+        let tall = BoundingRectangle::new((2, -3), (5, 6));
+        let odd = BoundingRectangle::new((0.0, 0.0), (5.0, 2.0));
+
+        let wide_square = wide.to_square();
+        let tall_square = tall.to_square();
+        let odd_square = odd.to_square();
+
+        assert_eq!((wide_square.width(), wide_square.height()), (10, 10));
+        assert_eq!((wide_square.left, wide_square.right), ((0, -3), (10, 7)));
+        assert!(wide_square.contains(&wide));
+        assert_eq!((tall_square.width(), tall_square.height()), (9, 9));
+        assert!(tall_square.contains(&tall));
+        assert_eq!(odd_square.center(), odd.center());
+        assert_eq!(odd_square.width(), 5.0);
+    }
+
+    #[test]
+    fn test_to_wkt() {
+        assert_eq!(RtreeGeometry::new(vec!((1, 2))).to_wkt(), "POINT (1 2)");
+        assert_eq!(RtreeGeometry::new(vec!((1, 2), (3, 4))).to_wkt(), "LINESTRING (1 2, 3 4)");
+        assert_eq!(
+            RtreeGeometry::new(vec!((0, 0), (4, 0), (2, 3), (0, 0))).to_wkt(),
+            "POLYGON ((0 0, 4 0, 2 3, 0 0))"
+        );
+        assert_eq!(RtreeGeometry::new(vec!((0.5, -1.25))).to_wkt(), "POINT (0.5 -1.25)");
+    }
 }
@@ -1,15 +1,129 @@
-use std::f64::{INFINITY, NEG_INFINITY};
-use std::fmt::{Display, Debug, Formatter};
-use std::fmt::Result as FmtResult;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Div, Mul, Sub};
 
-pub type Coordinates = (i64, i64);
-pub type Geometry = Vec<Coordinates>;
-pub type Geoms = Vec<Geometry>;
+pub type Coordinates<S = i64> = (S, S);
+pub type Geometry<S = i64> = Vec<Coordinates<S>>;
+pub type Geoms<S = i64> = Vec<Geometry<S>>;
 
-const INF: i64 = INFINITY as i64;
-const NEGINF: i64 = NEG_INFINITY as i64;
+pub trait Scalar: Copy + PartialOrd + Display + Debug
+        + Add<Output = Self> + Sub<Output = Self>
+        + Mul<Output = Self> + Div<Output = Self> {
+    fn min_value() -> Self;
+    fn max_value() -> Self;
+    fn zero() -> Self;
+    fn one() -> Self;
+    fn to_f64(self) -> f64;
+    fn from_f64(value: f64) -> Self;
+    fn saturating_add(self, other: Self) -> Self;
+    fn saturating_sub(self, other: Self) -> Self;
+    fn midpoint(self, other: Self) -> Self;
+    fn area(left: Coordinates<Self>, right: Coordinates<Self>) -> Self;
+}
 
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                fn min_value() -> Self {
+                    <$t>::MIN
+                }
+
+                fn max_value() -> Self {
+                    <$t>::MAX
+                }
+
+                fn zero() -> Self {
+                    0
+                }
+
+                fn one() -> Self {
+                    1
+                }
+
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+
+                fn from_f64(value: f64) -> Self {
+                    value as $t
+                }
+
+                fn saturating_add(self, other: Self) -> Self {
+                    <$t>::saturating_add(self, other)
+                }
+
+                fn saturating_sub(self, other: Self) -> Self {
+                    <$t>::saturating_sub(self, other)
+                }
+
+                fn midpoint(self, other: Self) -> Self {
+                    (self as i128 + other as i128).div_euclid(2) as $t
+                }
+
+                fn area(left: Coordinates<Self>, right: Coordinates<Self>) -> Self {
+                    let width = right.0 as i128 - left.0 as i128;
+                    let height = right.1 as i128 - left.1 as i128;
+                    match width.checked_mul(height) {
+                        Some(area) if area > <$t>::MAX as i128 => <$t>::MAX,
+                        Some(area) if area < <$t>::MIN as i128 => <$t>::MIN,
+                        Some(area) => area as $t,
+                        None if (width < 0) == (height < 0) => <$t>::MAX,
+                        None => <$t>::MIN
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(i32, i64);
+
+impl Scalar for f64 {
+    fn min_value() -> Self {
+        f64::MIN
+    }
+
+    fn max_value() -> Self {
+        f64::MAX
+    }
+
+    fn zero() -> Self {
+        0.0
+    }
+
+    fn one() -> Self {
+        1.0
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+
+    fn from_f64(value: f64) -> Self {
+        value
+    }
+
+    fn saturating_add(self, other: Self) -> Self {
+        self + other
+    }
+
+    fn saturating_sub(self, other: Self) -> Self {
+        self - other
+    }
+
+    fn midpoint(self, other: Self) -> Self {
+        self + (other - self) / 2.0
+    }
+
+    fn area(left: Coordinates<Self>, right: Coordinates<Self>) -> Self {
+        (right.0 - left.0) * (right.1 - left.1)
+    }
+}
+
+pub mod errors;
+pub mod frozen;
 pub mod geometries;
 pub mod nodes;
 pub mod split;
-mod utils;
\ No newline at end of file
+pub mod tree;
+mod utils;
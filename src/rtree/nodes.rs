@@ -1,41 +1,83 @@
 use std::cell::RefCell;
-use std::fmt::{Display, Debug, Formatter};
+use std::cmp;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fmt::Result as FmtResult;
-use std::mem::MaybeUninit;
+use std::io;
+use std::io::Write;
+use std::mem;
+use std::ops::ControlFlow;
 use std::rc::{Rc, Weak};
 
-use crate::{INF, NEGINF};
-use crate::geometries::{BoundingRectangle, RtreeGeometry};
+#[cfg(feature = "rand")]
+use rand::Rng;
+
+use crate::{Coordinates, Geometry, Scalar};
+use crate::errors::RtreeError;
+use crate::frozen::FrozenRtree;
+use crate::geometries::{Boundary, BoundingRectangle, Corner, GeometryType, RtreeGeometry};
+use crate::split;
 use crate::split::RtreeSplit;
-use crate::utils::{find_least_enlargement, generate_id};
+use crate::utils::{find_least_enlargement, generate_id, partial_max, IdSource};
 
 
-pub type TreeNode = Rc<RefCell<RtreeNode>>;
-pub type TreeGeometry = Rc<RefCell<RtreeGeometry>>;
+pub type TreeNode<S = i64> = Rc<RefCell<RtreeNode<S>>>;
+pub type TreeGeometry<S = i64> = Rc<RefCell<RtreeGeometry<S>>>;
 
 
-pub trait RtreeObject {
+pub trait RtreeObject<S: Scalar = i64> {
     fn id(&self) -> &str;
-    fn mbr(&self) -> &BoundingRectangle;
-    fn set_mbr(&mut self, mbr: BoundingRectangle) -> ();
-    fn set_parent(&mut self, node: &TreeNode) -> ();
+    fn mbr(&self) -> &BoundingRectangle<S>;
+    fn set_mbr(&mut self, mbr: BoundingRectangle<S>);
+    fn set_parent(&mut self, node: &TreeNode<S>);
 }
 
-pub enum ChildrenType {
-    InnerNodes(Vec<TreeNode>),
-    Leafs(Vec<TreeGeometry>)
+pub enum ChildrenType<S: Scalar = i64> {
+    InnerNodes(Vec<TreeNode<S>>),
+    Leafs(Vec<TreeGeometry<S>>)
 }
 
-pub struct RtreeNode {
+pub struct RtreeNode<S: Scalar = i64> {
     pub id: String,
-    pub children: ChildrenType,
-    pub mbr: BoundingRectangle,
+    pub children: ChildrenType<S>,
+    pub mbr: BoundingRectangle<S>,
     pub max_children: u8,
-    pub parent: Option<Weak<RefCell<RtreeNode>>>
+    pub parent: Option<Weak<RefCell<RtreeNode<S>>>>,
+    pub(crate) ids: IdSource,
+    height: usize
+}
+
+#[derive(Copy, Clone)]
+#[derive(PartialEq, Eq, Debug)]
+pub enum Walk {
+    Prune,
+    Stop
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Violation {
+    LooseMbr(String),
+    MissingParent(String),
+    UnevenLeafDepth { id: String, depth: usize, expected: usize }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct RepairReport {
+    pub repaired_mbrs: Vec<String>,
+    pub relinked_parents: Vec<String>,
+    pub unfixable: Vec<Violation>
+}
+
+pub struct WindowQuery<S: Scalar = i64> {
+    query: BoundingRectangle<S>,
+    nodes: Vec<TreeNode<S>>,
+    leafs: Vec<TreeGeometry<S>>,
+    remaining: usize
 }
 
-impl ChildrenType {
-    fn len(&self) -> usize {
+impl<S: Scalar> ChildrenType<S> {
+    pub fn len(&self) -> usize {
         match self {
             Self::InnerNodes(ref nodes) => {
                 nodes.len()
@@ -46,32 +88,105 @@ impl ChildrenType {
         }
     }
 
-    fn add_node(&mut self, object: TreeNode) {
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn capacity(&self) -> usize {
+        match self {
+            Self::InnerNodes(ref nodes) => {
+                nodes.capacity()
+            },
+            Self::Leafs(ref leafs) => {
+                leafs.capacity()
+            }
+        }
+    }
+
+    pub fn reserve(&mut self, additional: usize) {
+        match self {
+            Self::InnerNodes(ref mut nodes) => {
+                nodes.reserve(additional)
+            },
+            Self::Leafs(ref mut leafs) => {
+                leafs.reserve(additional)
+            }
+        }
+    }
+
+    pub fn mbrs(&self) -> Vec<BoundingRectangle<S>> {
+        match self {
+            Self::InnerNodes(ref nodes) => {
+                nodes.iter().map(|node| *node.borrow().mbr()).collect()
+            },
+            Self::Leafs(ref leafs) => {
+                leafs.iter().map(|leaf| *leaf.borrow().mbr()).collect()
+            }
+        }
+    }
+
+    pub fn common_mbr(&self) -> BoundingRectangle<S> {
+        BoundingRectangle::common_mbr(&self.mbrs().iter().collect())
+    }
+
+    pub fn add_node(&mut self, object: TreeNode<S>) {
         if let Self::InnerNodes(ref mut nodes) = self {
             nodes.push(object)
         }
     }
 
-    fn add_leaf(&mut self, object: TreeGeometry) {
+    pub fn add_leaf(&mut self, object: TreeGeometry<S>) {
         if let Self::Leafs(ref mut leafs) = self {
             leafs.push(object)
         };
     }
 }
 
-impl RtreeNode {
-    pub fn new<T>(rectangle: BoundingRectangle, max_children: u8, 
-                  obj_type: fn(Vec<T>) -> ChildrenType) -> RtreeNode {
+impl<S: Scalar> RtreeNode<S> {
+    pub fn new<T>(rectangle: BoundingRectangle<S>, max_children: u8, 
+                  obj_type: fn(Vec<T>) -> ChildrenType<S>) -> RtreeNode<S> {
+        RtreeNode::with_ids(rectangle, max_children, obj_type, IdSource::Uuid)
+    }
+
+    pub(crate) fn with_ids<T>(rectangle: BoundingRectangle<S>, max_children: u8,
+                              obj_type: fn(Vec<T>) -> ChildrenType<S>,
+                              ids: IdSource) -> RtreeNode<S> {
+        assert!(max_children >= 2, "max_children must be at least 2");
+        let children = obj_type(vec!());
+        let height = match children {
+            ChildrenType::InnerNodes(_) => 2,
+            ChildrenType::Leafs(_) => 1
+        };
         RtreeNode {
-            id: generate_id(),
-            children: obj_type(vec!()),
+            id: generate_id(&ids),
+            children,
             mbr: rectangle,
-            max_children: max_children,
-            parent: None
+            max_children,
+            parent: None,
+            ids,
+            height
+        }
+    }
+
+    pub(crate) fn from_children(children: ChildrenType<S>, max_children: u8,
+                                height: usize, ids: IdSource) -> RtreeNode<S> {
+        RtreeNode {
+            id: generate_id(&ids),
+            mbr: children.common_mbr(),
+            children,
+            max_children,
+            parent: None,
+            ids,
+            height
         }
     }
 
-    pub fn print(&self, prev: &str) -> () {
+    pub fn new_shared<T>(rectangle: BoundingRectangle<S>, max_children: u8,
+                         obj_type: fn(Vec<T>) -> ChildrenType<S>) -> TreeNode<S> {
+        Rc::new(RefCell::new(RtreeNode::new(rectangle, max_children, obj_type)))
+    }
+
+    pub fn print(&self, prev: &str) {
         println!("{}├──{}", prev, self);
         match &self.children {
             ChildrenType::InnerNodes(nodes) => {
@@ -88,55 +203,2538 @@ impl RtreeNode {
         }
     }
 
-    pub fn insert(&mut self, geom: TreeGeometry) -> () {
+    pub fn min_children(&self) -> usize {
+        cmp::max(self.max_children as usize / 2, 1)
+    }
+
+    pub fn cached_height(&self) -> usize {
+        self.height
+    }
+
+    pub fn height(&self) -> usize {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let mut max_height = 0;
+                for node in nodes {
+                    max_height = cmp::max(max_height, node.borrow().height());
+                }
+                max_height + 1
+            },
+            ChildrenType::Leafs(_) => 1
+        }
+    }
+
+    pub fn geometries(&self) -> Vec<TreeGeometry<S>> {
+        let mut found = vec!();
+        self.collect_geometries(&mut found);
+        found
+    }
+
+    fn collect_geometries(&self, found: &mut Vec<TreeGeometry<S>>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    node.borrow().collect_geometries(found)
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                found.extend(leafs.iter().cloned())
+            }
+        }
+    }
+
+    pub fn insert(&mut self, geom: TreeGeometry<S>) {
+        self.insert_traced(geom, None)
+    }
+
+    pub fn insert_instrumented(&mut self, geom: TreeGeometry<S>, trace: &mut Vec<String>) {
+        self.insert_traced(geom, Some(trace))
+    }
+
+    fn insert_traced(&mut self, geom: TreeGeometry<S>, trace: Option<&mut Vec<String>>) {
+        let mbr = geom.borrow().mbr;
+        if let ChildrenType::InnerNodes(ref nodes) = self.children {
+            if nodes.is_empty() {
+                self.children = ChildrenType::Leafs(vec!());
+                self.height = 1;
+            }
+        }
+        if self.children.is_empty() {
+            self.mbr = mbr;
+        }
+        let path = self.choose_path(&mbr, trace);
+        self.grow_path(&path, &mbr);
+        match path.last() {
+            Some(leaf) => {
+                geom.borrow_mut().set_parent(leaf);
+                leaf.borrow_mut().children.add_leaf(geom);
+            },
+            None => self.children.add_leaf(geom)
+        }
+        self.split_path(&path);
+    }
+
+    fn choose_path(&self, mbr: &BoundingRectangle<S>,
+                   mut trace: Option<&mut Vec<String>>) -> Vec<TreeNode<S>> {
+        let mut path: Vec<TreeNode<S>> = vec!();
+        let mut next = match &self.children {
+            ChildrenType::InnerNodes(nodes) => Some(find_least_enlargement(&mut nodes.clone(), mbr).0),
+            ChildrenType::Leafs(_) => None
+        };
+        while let Some(node) = next {
+            if let Some(trace) = trace.as_deref_mut() {
+                trace.push(node.borrow().id.clone());
+            }
+            next = match &node.borrow().children {
+                ChildrenType::InnerNodes(nodes) => Some(find_least_enlargement(&mut nodes.clone(), mbr).0),
+                ChildrenType::Leafs(_) => None
+            };
+            path.push(node);
+        }
+        path
+    }
+
+    fn grow_path(&mut self, path: &[TreeNode<S>], added: &BoundingRectangle<S>) {
+        for node in path.iter().rev() {
+            let mut node = node.borrow_mut();
+            if node.mbr.contains(added) {
+                return
+            }
+            node.mbr = node.mbr.union(added);
+        }
+        if !self.mbr.contains(added) {
+            self.mbr = self.mbr.union(added);
+        }
+    }
+
+    fn split_path(&mut self, path: &[TreeNode<S>]) {
+        let mut sibling: Option<RtreeNode<S>> = None;
+        for node in path.iter().rev() {
+            let mut node_ref = node.borrow_mut();
+            if let Some(split) = sibling.take() {
+                let split = Rc::new(RefCell::new(split));
+                RtreeNode::link_children(&split);
+                split.borrow_mut().set_parent(node);
+                node_ref.children.add_node(split);
+            }
+            if node_ref.children.len() <= node_ref.max_children as usize {
+                return
+            }
+            sibling = Some(node_ref.split());
+        }
+        if let Some(split) = sibling {
+            let split = Rc::new(RefCell::new(split));
+            RtreeNode::link_children(&split);
+            self.children.add_node(split);
+        }
+        if self.children.len() > self.max_children as usize {
+            let split = self.split();
+            self.grow_root(split);
+        }
+    }
+
+    fn grow_root(&mut self, sibling: RtreeNode<S>) {
+        let mbr = self.mbr.union(&sibling.mbr);
+        let height = self.height + 1;
+        let old_root = mem::replace(
+            self,
+            RtreeNode::with_ids(mbr, self.max_children, ChildrenType::InnerNodes, self.ids.clone())
+        );
+        self.height = height;
+        let old_root = Rc::new(RefCell::new(old_root));
+        let sibling = Rc::new(RefCell::new(sibling));
+        RtreeNode::link_children(&old_root);
+        RtreeNode::link_children(&sibling);
+        self.children = ChildrenType::InnerNodes(vec!(old_root, sibling));
+    }
+
+    fn link_children(handle: &TreeNode<S>) {
+        match &handle.borrow().children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    node.borrow_mut().set_parent(handle);
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    leaf.borrow_mut().set_parent(handle);
+                }
+            }
+        }
+    }
+
+    pub fn delete(&mut self, id: &str) -> Option<TreeGeometry<S>> {
+        let mut orphans = vec!();
+        let removed = self.remove_entry(id, &mut orphans)?;
+        self.condense_root();
+        for orphan in orphans {
+            self.insert(orphan)
+        }
+        Some(removed)
+    }
+
+    fn remove_entry(&mut self, id: &str, orphans: &mut Vec<TreeGeometry<S>>) -> Option<TreeGeometry<S>> {
+        let min_children = self.min_children();
         match &mut self.children {
-            ChildrenType::InnerNodes(ref mut objs) => {
-                let mut least_enl_vec: Vec<TreeNode> = vec!();
-                for obj in objs {
-                    least_enl_vec.push(obj.clone())
+            ChildrenType::InnerNodes(ref mut nodes) => {
+                for index in 0..nodes.len() {
+                    let removed = nodes[index].borrow_mut().remove_entry(id, orphans);
+                    if let Some(geom) = removed {
+                        if nodes[index].borrow().children.len() < min_children {
+                            let underfull = nodes.remove(index);
+                            underfull.borrow().collect_geometries(orphans);
+                        } else {
+                            let mbr = nodes[index].borrow().children.common_mbr();
+                            nodes[index].borrow_mut().set_mbr(mbr);
+                        }
+                        return Some(geom)
+                    }
                 }
-                let least_enl = find_least_enlargement(
-                    &mut least_enl_vec,
-                    &geom.borrow().mbr()
-                );
-                let obj_to_enl = least_enl.0;
-                obj_to_enl.borrow_mut().set_mbr(least_enl.1);
-                obj_to_enl.borrow_mut().insert(geom);
+                None
             },
-            ChildrenType::Leafs(ref mut objs) => {
-                objs.push(geom);
+            ChildrenType::Leafs(ref mut leafs) => {
+                let index = leafs.iter().position(|leaf| leaf.borrow().id == id)?;
+                Some(leafs.remove(index))
             }
         }
     }
-}
 
-impl PartialEq for RtreeNode {
-    fn eq(&self, other: &Self) -> bool {
-        self.id == other.id
+    fn condense_root(&mut self) {
+        loop {
+            let only_child = match &mut self.children {
+                ChildrenType::InnerNodes(ref mut nodes) if nodes.len() == 1 => nodes.pop(),
+                ChildrenType::InnerNodes(ref nodes) if nodes.is_empty() => {
+                    self.children = ChildrenType::Leafs(vec!());
+                    self.height = 1;
+                    None
+                },
+                _ => None
+            };
+            match only_child {
+                Some(child) => {
+                    let mut child = child.borrow_mut();
+                    self.children = mem::replace(&mut child.children, ChildrenType::Leafs(vec!()));
+                    self.height = child.height;
+                },
+                None => break
+            }
+        }
+        if !self.children.is_empty() {
+            self.mbr = self.children.common_mbr();
+        }
     }
-}
 
-impl Display for RtreeNode {
-    fn fmt(&self, f: &mut Formatter) -> FmtResult {
-        f.write_fmt(format_args!("Node {} with MBR {}", self.id, self.mbr))
+    pub fn query_window(&self, query: &BoundingRectangle<S>) -> Vec<TreeGeometry<S>> {
+        self.query_window_mode(query, Boundary::Inclusive)
     }
-}
 
-impl RtreeObject for RtreeNode {
+    pub fn query_window_mode(&self, query: &BoundingRectangle<S>,
+                             boundary: Boundary) -> Vec<TreeGeometry<S>> {
+        let mut found = vec!();
+        self.collect_window(query, boundary, &mut found);
+        found
+    }
 
-    fn id(&self) -> &str {
-        &self.id
+    fn collect_window(&self, query: &BoundingRectangle<S>, boundary: Boundary,
+                      found: &mut Vec<TreeGeometry<S>>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().intersects_with(query, boundary) {
+                        node.collect_window(query, boundary, found)
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    if leaf.borrow().mbr().intersects_with(query, boundary) {
+                        found.push(leaf.clone())
+                    }
+                }
+            }
+        }
     }
 
-    fn mbr(&self) -> &BoundingRectangle {
-        &self.mbr
+    pub fn query_window_ranked(&self, query: &BoundingRectangle<S>) -> Vec<(TreeGeometry<S>, f64)> {
+        let (query_x, query_y) = query.center_f64();
+        let mut ranked: Vec<(TreeGeometry<S>, f64)> = vec!();
+        for geom in self.query_window(query) {
+            let (x, y) = geom.borrow().mbr().center_f64();
+            let distance = (x - query_x).hypot(y - query_y);
+            ranked.push((geom, distance));
+        }
+        ranked.sort_by(|a, b| a.1.total_cmp(&b.1));
+        ranked
     }
 
-    fn set_mbr(&mut self, mbr: BoundingRectangle) -> () {
-        self.mbr = mbr
+    pub fn dead_space(&self) -> S {
+        let mut covered = S::zero();
+        for mbr in self.children.mbrs() {
+            covered = covered.saturating_add(mbr.area);
+        }
+        partial_max(self.mbr.area.saturating_sub(covered), S::zero())
     }
 
-    fn set_parent(&mut self, node: &TreeNode) -> () {
-        self.parent = Some(Rc::downgrade(node));
+    pub fn query_window_by_overlap(&self, query: &BoundingRectangle<S>) -> Vec<(TreeGeometry<S>, S)> {
+        let mut ranked: Vec<(TreeGeometry<S>, S)> = vec!();
+        for geom in self.query_window(query) {
+            let overlap = geom.borrow().mbr().overlap_area(query);
+            ranked.push((geom, overlap));
+        }
+        ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+        ranked
+    }
+
+    pub fn overlap_enlargement(&self, candidate_index: usize,
+                               new_mbr: &BoundingRectangle<S>) -> S {
+        let mbrs = self.children.mbrs();
+        let candidate = &mbrs[candidate_index];
+        let enlarged = candidate.union(new_mbr);
+
+        let mut enlargement = S::zero();
+        for (index, mbr) in mbrs.iter().enumerate() {
+            if index == candidate_index {
+                continue
+            }
+            let added = enlarged.overlap_area(mbr) - candidate.overlap_area(mbr);
+            enlargement = enlargement.saturating_add(added);
+        }
+        enlargement
+    }
+
+    pub fn join(&self, other: &RtreeNode<S>) -> Vec<(TreeGeometry<S>, TreeGeometry<S>)> {
+        let mut pairs = vec!();
+        self.join_with(other, |geom, other_geom| pairs.push((geom.clone(), other_geom.clone())));
+        pairs
+    }
+
+    pub fn join_with<F>(&self, other: &RtreeNode<S>, mut f: F)
+            where F: FnMut(&TreeGeometry<S>, &TreeGeometry<S>) {
+        self.join_nodes(other, &mut f)
+    }
+
+    fn join_nodes<F>(&self, other: &RtreeNode<S>, f: &mut F)
+            where F: FnMut(&TreeGeometry<S>, &TreeGeometry<S>) {
+        if self.children.is_empty() || other.children.is_empty() {
+            return
+        }
+        match (&self.children, &other.children) {
+            (ChildrenType::Leafs(leafs), ChildrenType::Leafs(other_leafs)) => {
+                for leaf in leafs {
+                    for other_leaf in other_leafs {
+                        if leaf.borrow().mbr().intersects(other_leaf.borrow().mbr()) {
+                            f(leaf, other_leaf)
+                        }
+                    }
+                }
+            },
+            (ChildrenType::InnerNodes(nodes), ChildrenType::InnerNodes(other_nodes))
+                    if self.height == other.height => {
+                for node in nodes {
+                    let node = node.borrow();
+                    for other_node in other_nodes {
+                        let other_node = other_node.borrow();
+                        if node.mbr().intersects(other_node.mbr()) {
+                            node.join_nodes(&other_node, f)
+                        }
+                    }
+                }
+            },
+            (ChildrenType::InnerNodes(nodes), _) if self.height >= other.height => {
+                let bounds = other.children.common_mbr();
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().intersects(&bounds) {
+                        node.join_nodes(other, f)
+                    }
+                }
+            },
+            (_, ChildrenType::InnerNodes(other_nodes)) => {
+                let bounds = self.children.common_mbr();
+                for other_node in other_nodes {
+                    let other_node = other_node.borrow();
+                    if other_node.mbr().intersects(&bounds) {
+                        self.join_nodes(&other_node, f)
+                    }
+                }
+            },
+            _ => {}
+        }
+    }
+
+    pub fn query_enclosing(&self, point_or_rect: &BoundingRectangle<S>) -> Vec<TreeGeometry<S>> {
+        let mut found = vec!();
+        self.collect_enclosing(point_or_rect, &mut found);
+        found
+    }
+
+    fn collect_enclosing(&self, query: &BoundingRectangle<S>, found: &mut Vec<TreeGeometry<S>>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().contains(query) {
+                        node.collect_enclosing(query, found)
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    if leaf.borrow().mbr().contains(query) {
+                        found.push(leaf.clone())
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn contains_id(&self, id: &str) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn get(&self, id: &str) -> Option<TreeGeometry<S>> {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                nodes.iter().find_map(|node| node.borrow().get(id))
+            },
+            ChildrenType::Leafs(leafs) => {
+                leafs.iter().find(|leaf| leaf.borrow().id == id).cloned()
+            }
+        }
+    }
+
+    pub fn set_coords(&mut self, id: &str, coords: Geometry<S>) -> Result<(), RtreeError> {
+        let unknown = || RtreeError::UnknownId(id.to_string());
+        let geom = self.get(id).ok_or_else(unknown)?;
+        let leaf_mbr = self.leaf_mbr_of(id).ok_or_else(unknown)?;
+
+        geom.borrow_mut().set_coords(coords)?;
+        let new_mbr = geom.borrow().mbr;
+        if leaf_mbr.contains(&new_mbr) {
+            self.refit_path(id);
+            self.mbr = self.children.common_mbr();
+        } else if let Some(geom) = self.delete(id) {
+            self.insert(geom);
+        }
+        Ok(())
+    }
+
+    pub fn propagate_mbr(&mut self, leaf_id: &str, added: &BoundingRectangle<S>) {
+        if let Some(path) = self.node_path(leaf_id) {
+            self.grow_path(&path, added);
+        }
+    }
+
+    fn node_path(&self, id: &str) -> Option<Vec<TreeNode<S>>> {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                nodes.iter().find_map(|node| {
+                    let mut path = node.borrow().node_path(id)?;
+                    path.insert(0, node.clone());
+                    Some(path)
+                })
+            },
+            ChildrenType::Leafs(leafs) => {
+                if leafs.iter().any(|leaf| leaf.borrow().id == id) {
+                    Some(vec!())
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn leaf_mbr_of(&self, id: &str) -> Option<BoundingRectangle<S>> {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                nodes.iter().find_map(|node| node.borrow().leaf_mbr_of(id))
+            },
+            ChildrenType::Leafs(leafs) => {
+                if leafs.iter().any(|leaf| leaf.borrow().id == id) {
+                    Some(self.mbr)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn refit_path(&mut self, id: &str) -> bool {
+        match &mut self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let mut node = node.borrow_mut();
+                    if node.refit_path(id) {
+                        node.mbr = node.children.common_mbr();
+                        return true
+                    }
+                }
+                false
+            },
+            ChildrenType::Leafs(leafs) => {
+                leafs.iter().any(|leaf| leaf.borrow().id == id)
+            }
+        }
+    }
+
+    pub fn into_geometries(self) -> Vec<RtreeGeometry<S>> {
+        let geoms = self.geometries();
+        drop(self);
+        geoms.into_iter()
+            .map(|geom| match Rc::try_unwrap(geom) {
+                Ok(geom) => geom.into_inner(),
+                Err(geom) => geom.borrow().clone()
+            })
+            .collect()
+    }
+
+    pub fn query_window_cornered(&self, query: &BoundingRectangle<S>) -> Vec<(TreeGeometry<S>, Corner)> {
+        let query_center = query.center_f64();
+        let mut cornered = vec!();
+        for geom in self.query_window(query) {
+            let center = geom.borrow().mbr().center_f64();
+            let corner = match (center.0 < query_center.0, center.1 < query_center.1) {
+                (true, true) => Corner::BottomLeft,
+                (false, true) => Corner::BottomRight,
+                (true, false) => Corner::TopLeft,
+                (false, false) => Corner::TopRight
+            };
+            cornered.push((geom, corner));
+        }
+        cornered
+    }
+
+    pub fn nearest_k(&self, point: Coordinates<S>, k: usize) -> Vec<(TreeGeometry<S>, f64)> {
+        let mut best = vec!();
+        self.search_nearest(point, k, &mut best, &|geom: &TreeGeometry<S>| geom.clone());
+        best.into_iter().map(|(distance, geom)| (geom, distance)).collect()
+    }
+
+    pub fn kth_nearest_distance(&self, point: Coordinates<S>, k: usize) -> Option<f64> {
+        let mut best = vec!();
+        self.search_nearest(point, k, &mut best, &|_: &TreeGeometry<S>| ());
+        if k > 0 && best.len() == k {
+            best.last().map(|entry| entry.0)
+        } else {
+            None
+        }
+    }
+
+    fn search_nearest<T, F>(&self, point: Coordinates<S>, k: usize,
+                            best: &mut Vec<(f64, T)>, make: &F)
+            where F: Fn(&TreeGeometry<S>) -> T {
+        if k == 0 {
+            return
+        }
+        let pruned = |best: &Vec<(f64, T)>, distance: f64| {
+            best.len() == k && best.last().is_some_and(|entry| distance > entry.0)
+        };
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let mut ordered: Vec<(f64, &TreeNode<S>)> = nodes.iter()
+                    .map(|node| (node.borrow().mbr().min_dist(point), node))
+                    .collect();
+                ordered.sort_by(|a, b| a.0.total_cmp(&b.0));
+                for (distance, node) in ordered {
+                    if pruned(best, distance) {
+                        break
+                    }
+                    node.borrow().search_nearest(point, k, best, make);
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    let distance = leaf.borrow().mbr().min_dist(point);
+                    if best.len() == k && distance >= best[k - 1].0 {
+                        continue
+                    }
+                    let index = best.partition_point(|entry| entry.0 <= distance);
+                    best.insert(index, (distance, make(leaf)));
+                    best.truncate(k);
+                }
+            }
+        }
+    }
+
+    pub fn neighbors_of(&self, id: &str) -> Vec<TreeGeometry<S>> {
+        let mbr = match self.get(id) {
+            Some(geom) => geom.borrow().mbr,
+            None => return vec!()
+        };
+        self.query_window(&mbr).into_iter()
+            .filter(|geom| geom.borrow().id != id)
+            .collect()
+    }
+
+    pub fn walk<F>(&self, mut f: F)
+            where F: FnMut(&RtreeNode<S>, usize) -> ControlFlow<Walk> {
+        let _ = self.walk_depth(0, &mut f);
+    }
+
+    fn walk_depth<F>(&self, depth: usize, f: &mut F) -> ControlFlow<()>
+            where F: FnMut(&RtreeNode<S>, usize) -> ControlFlow<Walk> {
+        match f(self, depth) {
+            ControlFlow::Break(Walk::Stop) => return ControlFlow::Break(()),
+            ControlFlow::Break(Walk::Prune) => return ControlFlow::Continue(()),
+            ControlFlow::Continue(()) => {}
+        }
+        if let ChildrenType::InnerNodes(nodes) = &self.children {
+            for node in nodes {
+                node.borrow().walk_depth(depth + 1, f)?;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    pub fn reverse_nearest(&self, id: &str) -> Vec<TreeGeometry<S>> {
+        let target = match self.get(id) {
+            Some(geom) => geom,
+            None => return vec!()
+        };
+        let target_mbr = target.borrow().mbr;
+
+        let mut found = vec!();
+        for geom in self.geometries() {
+            let (geom_id, mbr) = {
+                let geom = geom.borrow();
+                (geom.id.clone(), geom.mbr)
+            };
+            if geom_id == id {
+                continue
+            }
+            let bound = mbr.min_dist_rect(&target_mbr);
+            if !self.has_closer(&mbr, bound, &[&geom_id, id]) {
+                found.push(geom)
+            }
+        }
+        found
+    }
+
+    fn has_closer(&self, mbr: &BoundingRectangle<S>, bound: f64, exclude: &[&str]) -> bool {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                nodes.iter().any(|node| {
+                    let node = node.borrow();
+                    node.mbr().min_dist_rect(mbr) < bound && node.has_closer(mbr, bound, exclude)
+                })
+            },
+            ChildrenType::Leafs(leafs) => {
+                leafs.iter().any(|leaf| {
+                    let leaf = leaf.borrow();
+                    !exclude.contains(&leaf.id()) && leaf.mbr().min_dist_rect(mbr) < bound
+                })
+            }
+        }
+    }
+
+    pub fn path_to(&self, id: &str) -> Option<Vec<String>> {
+        let mut path = match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                nodes.iter().find_map(|node| node.borrow().path_to(id))?
+            },
+            ChildrenType::Leafs(leafs) => {
+                if !leafs.iter().any(|leaf| leaf.borrow().id == id) {
+                    return None
+                }
+                vec!()
+            }
+        };
+        path.insert(0, self.id.clone());
+        Some(path)
+    }
+
+    pub fn query_windows(&self, queries: &[BoundingRectangle<S>]) -> Vec<Vec<TreeGeometry<S>>> {
+        let mut results = vec!(vec!(); queries.len());
+        let active: Vec<usize> = (0..queries.len()).collect();
+        self.collect_windows(queries, &active, &mut results);
+        results
+    }
+
+    fn collect_windows(&self, queries: &[BoundingRectangle<S>], active: &[usize],
+                       results: &mut Vec<Vec<TreeGeometry<S>>>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let mut node_active = Vec::with_capacity(active.len());
+                for node in nodes {
+                    let node = node.borrow();
+                    node_active.clear();
+                    node_active.extend(
+                        active.iter().copied().filter(|&index| node.mbr().intersects(&queries[index]))
+                    );
+                    if !node_active.is_empty() {
+                        node.collect_windows(queries, &node_active, results)
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    let mbr = leaf.borrow().mbr;
+                    for &index in active {
+                        if mbr.intersects(&queries[index]) {
+                            results[index].push(leaf.clone())
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn query_window_bucketed<F>(&self, query: &BoundingRectangle<S>,
+                                    bucket: F) -> HashMap<(i64, i64), Vec<TreeGeometry<S>>>
+            where F: Fn(Coordinates<S>) -> (i64, i64) {
+        let mut buckets = HashMap::new();
+        self.collect_bucketed(query, &bucket, &mut buckets);
+        buckets
+    }
+
+    fn collect_bucketed<F>(&self, query: &BoundingRectangle<S>, bucket: &F,
+                           buckets: &mut HashMap<(i64, i64), Vec<TreeGeometry<S>>>)
+            where F: Fn(Coordinates<S>) -> (i64, i64) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().intersects(query) {
+                        node.collect_bucketed(query, bucket, buckets)
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    let mbr = leaf.borrow().mbr;
+                    if mbr.intersects(query) {
+                        buckets.entry(bucket(mbr.center())).or_default().push(leaf.clone())
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn query_window_limited(&self, query: &BoundingRectangle<S>,
+                                limit: usize) -> Vec<TreeGeometry<S>> {
+        let mut found = vec!();
+        if limit > 0 {
+            self.collect_limited(query, limit, &mut found);
+        }
+        found
+    }
+
+    fn collect_limited(&self, query: &BoundingRectangle<S>, limit: usize,
+                       found: &mut Vec<TreeGeometry<S>>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().intersects(query) {
+                        node.collect_limited(query, limit, found);
+                        if found.len() >= limit {
+                            return
+                        }
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    if leaf.borrow().mbr().intersects(query) {
+                        found.push(leaf.clone());
+                        if found.len() >= limit {
+                            return
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn query_obb(&self, center: Coordinates<S>, half_extents: (S, S),
+                     angle_rad: f64) -> Vec<TreeGeometry<S>> {
+        let (sin, cos) = angle_rad.sin_cos();
+        let (center_x, center_y) = (center.0.to_f64(), center.1.to_f64());
+        let (half_x, half_y) = (half_extents.0.to_f64(), half_extents.1.to_f64());
+        let offsets = [(-half_x, -half_y), (half_x, -half_y), (half_x, half_y), (-half_x, half_y)];
+        let corners: Vec<(f64, f64)> = offsets.iter()
+            .map(|&(dx, dy)| (center_x + dx * cos - dy * sin, center_y + dx * sin + dy * cos))
+            .collect();
+
+        let min_x = corners.iter().map(|corner| corner.0).fold(f64::INFINITY, f64::min);
+        let min_y = corners.iter().map(|corner| corner.1).fold(f64::INFINITY, f64::min);
+        let max_x = corners.iter().map(|corner| corner.0).fold(f64::NEG_INFINITY, f64::max);
+        let max_y = corners.iter().map(|corner| corner.1).fold(f64::NEG_INFINITY, f64::max);
+        let aabb = BoundingRectangle::new(
+            (S::from_f64(min_x.floor()), S::from_f64(min_y.floor())),
+            (S::from_f64(max_x.ceil()), S::from_f64(max_y.ceil()))
+        );
+
+        self.query_window(&aabb).into_iter()
+            .filter(|geom| geom.borrow().mbr.intersects_convex(&corners))
+            .collect()
+    }
+
+    pub fn nearest_to_geometry(&self, geom: &RtreeGeometry<S>) -> Option<(TreeGeometry<S>, f64)> {
+        let mut best = None;
+        self.search_nearest_geometry(geom, &mut best);
+        best
+    }
+
+    fn search_nearest_geometry(&self, geom: &RtreeGeometry<S>,
+                               best: &mut Option<(TreeGeometry<S>, f64)>) {
+        let pruned = |best: &Option<(TreeGeometry<S>, f64)>, distance: f64| {
+            best.as_ref().is_some_and(|entry| distance >= entry.1)
+        };
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let mut ordered: Vec<(f64, &TreeNode<S>)> = nodes.iter()
+                    .map(|node| (node.borrow().mbr().min_dist_rect(&geom.mbr), node))
+                    .collect();
+                ordered.sort_by(|a, b| a.0.total_cmp(&b.0));
+                for (distance, node) in ordered {
+                    if pruned(best, distance) {
+                        break
+                    }
+                    node.borrow().search_nearest_geometry(geom, best);
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    let candidate = leaf.borrow();
+                    if candidate.id == geom.id || pruned(best, candidate.mbr.min_dist_rect(&geom.mbr)) {
+                        continue
+                    }
+                    let distance = candidate.distance_to(geom);
+                    if !pruned(best, distance) {
+                        *best = Some((leaf.clone(), distance));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reserves room in this node's own children vector only. A split moves
+    /// entries into freshly allocated vectors, so the hint lasts until the
+    /// node first overflows.
+    pub fn reserve(&mut self, additional: usize) {
+        self.children.reserve(additional)
+    }
+
+    pub fn iter_window(&self, query: BoundingRectangle<S>) -> WindowQuery<S> {
+        WindowQuery::new(self, query)
+    }
+
+    pub fn iter_window_owned(&self, query: BoundingRectangle<S>) -> impl Iterator<Item = Geometry<S>> {
+        self.iter_window(query).map(|geom| geom.borrow().coords.clone())
+    }
+
+    pub fn preview_split(&self) -> Option<(BoundingRectangle<S>, BoundingRectangle<S>, usize, usize)> {
+        if self.children.len() < 2 {
+            return None
+        }
+        let min_children = self.min_children();
+        let (mbrs_1, mbrs_2) = match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let (group_1, group_2) = split::execute(nodes.clone(), min_children);
+                (ChildrenType::InnerNodes(group_1).mbrs(), ChildrenType::InnerNodes(group_2).mbrs())
+            },
+            ChildrenType::Leafs(leafs) => {
+                let (group_1, group_2) = split::execute(leafs.clone(), min_children);
+                (ChildrenType::Leafs(group_1).mbrs(), ChildrenType::Leafs(group_2).mbrs())
+            }
+        };
+        Some((
+            BoundingRectangle::common_mbr(&mbrs_1.iter().collect()),
+            BoundingRectangle::common_mbr(&mbrs_2.iter().collect()),
+            mbrs_1.len(),
+            mbrs_2.len()
+        ))
+    }
+
+    pub fn geometry_count(&self) -> usize {
+        let mut count = 0;
+        self.walk(|node, _| {
+            if let ChildrenType::Leafs(leafs) = &node.children {
+                count += leafs.len();
+            }
+            ControlFlow::Continue(())
+        });
+        count
+    }
+
+    pub fn type_counts(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for geom in self.geometries() {
+            match geom.borrow().coordtype {
+                GeometryType::Point => counts.0 += 1,
+                GeometryType::Line => counts.1 += 1,
+                GeometryType::Polygon => counts.2 += 1
+            }
+        }
+        counts
+    }
+
+    pub fn find_point(&self, coord: Coordinates<S>) -> Vec<TreeGeometry<S>> {
+        self.query_window(&BoundingRectangle::new(coord, coord)).into_iter()
+            .filter(|geom| {
+                let geom = geom.borrow();
+                geom.coordtype == GeometryType::Point && geom.mbr.left == coord && geom.mbr.right == coord
+            })
+            .collect()
+    }
+
+    pub fn iter_by_insertion_order(&self) -> Vec<TreeGeometry<S>> {
+        let mut geoms = self.geometries();
+        geoms.sort_by_key(|geom| geom.borrow().sequence());
+        geoms
+    }
+
+    #[cfg(feature = "rand")]
+    pub fn sample<R: Rng>(&self, n: usize, rng: &mut R) -> Vec<TreeGeometry<S>> {
+        let mut weighted: Vec<(f64, TreeGeometry<S>)> = vec!();
+        let mut total = 0.0;
+        for geom in self.geometries() {
+            let area = geom.borrow().mbr.area.to_f64();
+            if area > 0.0 {
+                total += area;
+                weighted.push((total, geom));
+            }
+        }
+        if weighted.is_empty() {
+            return vec!()
+        }
+
+        let mut sampled = Vec::with_capacity(n);
+        for _ in 0..n {
+            let target = rng.gen_range(0.0..total);
+            let index = weighted.partition_point(|entry| entry.0 <= target);
+            sampled.push(weighted[index.min(weighted.len() - 1)].1.clone());
+        }
+        sampled
+    }
+
+    pub fn hot_nodes(&self, overlap_threshold: f64) -> Vec<String> {
+        let mut hot = vec!();
+        self.walk(|node, _| {
+            if let ChildrenType::InnerNodes(nodes) = &node.children {
+                for (index, child) in nodes.iter().enumerate() {
+                    let child = child.borrow();
+                    let area = child.mbr.area.to_f64();
+                    if area <= 0.0 {
+                        continue
+                    }
+                    let mut overlap = 0.0;
+                    for (other_index, other) in nodes.iter().enumerate() {
+                        if other_index != index {
+                            overlap += child.mbr.overlap_area(other.borrow().mbr()).to_f64();
+                        }
+                    }
+                    if overlap / area > overlap_threshold {
+                        hot.push(child.id.clone())
+                    }
+                }
+            }
+            ControlFlow::Continue(())
+        });
+        hot
+    }
+
+    pub fn split_separation(&self) -> Option<f64> {
+        let (mbr_1, mbr_2) = self.seed_mbrs()?;
+        let combined = mbr_1.union(&mbr_2);
+        let diagonal = combined.width().to_f64().hypot(combined.height().to_f64());
+        if diagonal == 0.0 {
+            return Some(0.0)
+        }
+        Some(mbr_1.min_dist_rect(&mbr_2) / diagonal)
+    }
+
+    fn seed_mbrs(&self) -> Option<(BoundingRectangle<S>, BoundingRectangle<S>)> {
+        if self.children.len() < 2 {
+            return None
+        }
+        let (seed_1, seed_2) = match &self.children {
+            ChildrenType::InnerNodes(nodes) => split::pick_seeds(nodes),
+            ChildrenType::Leafs(leafs) => split::pick_seeds(leafs)
+        };
+        let mbrs = self.children.mbrs();
+        Some((mbrs[seed_1], mbrs[seed_2]))
+    }
+
+    pub fn query_window_by_leaf(&self, query: &BoundingRectangle<S>) -> Vec<(String, Vec<TreeGeometry<S>>)> {
+        let mut groups = vec!();
+        self.collect_by_leaf(query, &mut groups);
+        groups
+    }
+
+    fn collect_by_leaf(&self, query: &BoundingRectangle<S>,
+                       groups: &mut Vec<(String, Vec<TreeGeometry<S>>)>) {
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let node = node.borrow();
+                    if node.mbr().intersects(query) {
+                        node.collect_by_leaf(query, groups)
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                let found: Vec<TreeGeometry<S>> = leafs.iter()
+                    .filter(|leaf| leaf.borrow().mbr().intersects(query))
+                    .cloned()
+                    .collect();
+                if !found.is_empty() {
+                    groups.push((self.id.clone(), found))
+                }
+            }
+        }
+    }
+
+    pub fn freeze(self) -> FrozenRtree<S> {
+        FrozenRtree::from_root(self)
+    }
+
+    pub fn write_wkt<W: Write>(&self, mut writer: W) -> io::Result<usize> {
+        let mut written = 0;
+        let mut result = Ok(());
+        self.walk(|node, _| {
+            if let ChildrenType::Leafs(leafs) = &node.children {
+                for leaf in leafs {
+                    result = writeln!(writer, "{}", leaf.borrow().to_wkt());
+                    if result.is_err() {
+                        return ControlFlow::Break(Walk::Stop)
+                    }
+                    written += 1;
+                }
+            }
+            ControlFlow::Continue(())
+        });
+        result.map(|_| written)
+    }
+
+    pub fn assign_to_tiles(&self, tile_size: i64) -> HashMap<(i64, i64), Vec<TreeGeometry<S>>> {
+        assert!(tile_size > 0, "tile_size must be greater than zero");
+        let size = tile_size as f64;
+        let mut tiles: HashMap<(i64, i64), Vec<TreeGeometry<S>>> = HashMap::new();
+        for geom in self.geometries() {
+            let center = geom.borrow().mbr.center();
+            let tile = (
+                (center.0.to_f64() / size).floor() as i64,
+                (center.1.to_f64() / size).floor() as i64
+            );
+            tiles.entry(tile).or_default().push(geom);
+        }
+        tiles
+    }
+
+    pub fn insert_point(&mut self, coord: Coordinates<S>) -> String {
+        let geom = RtreeGeometry::point(coord);
+        let id = geom.id.clone();
+        self.insert(Rc::new(RefCell::new(geom)));
+        id
+    }
+
+    pub fn query_window_buffered(&self, query: &BoundingRectangle<S>,
+                                 tolerance: S) -> Vec<TreeGeometry<S>> {
+        self.query_window(&query.buffer(tolerance))
+    }
+
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = vec!();
+        self.validate_node(None, 0, &mut None, &mut violations);
+        violations
+    }
+
+    fn validate_node(&self, handle: Option<&TreeNode<S>>, depth: usize,
+                     leaf_depth: &mut Option<usize>, violations: &mut Vec<Violation>) {
+        if !self.children.is_empty() {
+            let tight = self.children.common_mbr();
+            if tight.left != self.mbr.left || tight.right != self.mbr.right {
+                violations.push(Violation::LooseMbr(self.id.clone()));
+            }
+        }
+        let linked = |parent: Option<TreeNode<S>>| match (handle, parent) {
+            (Some(handle), Some(parent)) => Rc::ptr_eq(handle, &parent),
+            (Some(_), None) => false,
+            (None, _) => true
+        };
+        match &self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes {
+                    let child = node.borrow();
+                    if !linked(child.parent.as_ref().and_then(Weak::upgrade)) {
+                        violations.push(Violation::MissingParent(child.id.clone()));
+                    }
+                    child.validate_node(Some(node), depth + 1, leaf_depth, violations);
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs {
+                    let leaf = leaf.borrow();
+                    if !linked(leaf.parent_node()) {
+                        violations.push(Violation::MissingParent(leaf.id.clone()));
+                    }
+                }
+                match *leaf_depth {
+                    None => *leaf_depth = Some(depth),
+                    Some(expected) if expected != depth => {
+                        violations.push(Violation::UnevenLeafDepth { id: self.id.clone(), depth, expected });
+                    },
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+        for violation in self.validate() {
+            match violation {
+                Violation::LooseMbr(_) | Violation::MissingParent(_) => {},
+                unfixable => report.unfixable.push(unfixable)
+            }
+        }
+        self.repair_node(None, &mut report);
+        report
+    }
+
+    fn repair_node(&mut self, handle: Option<&TreeNode<S>>, report: &mut RepairReport) {
+        match &mut self.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for node in nodes.iter() {
+                    if let Some(handle) = handle {
+                        let mut child = node.borrow_mut();
+                        let linked = child.parent.as_ref()
+                            .and_then(Weak::upgrade)
+                            .is_some_and(|parent| Rc::ptr_eq(handle, &parent));
+                        if !linked {
+                            child.set_parent(handle);
+                            report.relinked_parents.push(child.id.clone());
+                        }
+                    }
+                    node.borrow_mut().repair_node(Some(node), report);
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                if let Some(handle) = handle {
+                    for leaf in leafs.iter() {
+                        let mut leaf = leaf.borrow_mut();
+                        if !leaf.parent_node().is_some_and(|parent| Rc::ptr_eq(handle, &parent)) {
+                            leaf.set_parent(handle);
+                            report.relinked_parents.push(leaf.id.clone());
+                        }
+                    }
+                }
+            }
+        }
+        if !self.children.is_empty() {
+            let tight = self.children.common_mbr();
+            if tight.left != self.mbr.left || tight.right != self.mbr.right {
+                self.mbr = tight;
+                report.repaired_mbrs.push(self.id.clone());
+            }
+        }
+    }
+}
+
+impl<S: Scalar> Drop for RtreeNode<S> {
+    fn drop(&mut self) {
+        let mut stack = match &mut self.children {
+            ChildrenType::InnerNodes(ref mut nodes) => mem::take(nodes),
+            ChildrenType::Leafs(_) => return
+        };
+        while let Some(node) = stack.pop() {
+            if let Ok(node) = Rc::try_unwrap(node) {
+                if let ChildrenType::InnerNodes(ref mut nodes) = node.borrow_mut().children {
+                    stack.append(nodes);
+                }
+            }
+        }
+    }
+}
+
+impl<S: Scalar> PartialEq for RtreeNode<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<S: Scalar> Display for RtreeNode<S> {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        f.write_fmt(format_args!("Node {} with MBR {}", self.id, self.mbr))
+    }
+}
+
+impl<S: Scalar> RtreeObject<S> for RtreeNode<S> {
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn mbr(&self) -> &BoundingRectangle<S> {
+        &self.mbr
+    }
+
+    fn set_mbr(&mut self, mbr: BoundingRectangle<S>) {
+        self.mbr = mbr
+    }
+
+    fn set_parent(&mut self, node: &TreeNode<S>) {
+        self.parent = Some(Rc::downgrade(node));
+    }
+}
+
+impl<S: Scalar> WindowQuery<S> {
+    fn new(root: &RtreeNode<S>, query: BoundingRectangle<S>) -> WindowQuery<S> {
+        let mut window = WindowQuery {
+            query,
+            nodes: vec!(),
+            leafs: vec!(),
+            remaining: root.geometry_count()
+        };
+        window.expand(root);
+        window
+    }
+
+    fn expand(&mut self, node: &RtreeNode<S>) {
+        match &node.children {
+            ChildrenType::InnerNodes(nodes) => {
+                for child in nodes.iter().rev() {
+                    if child.borrow().mbr().intersects(&self.query) {
+                        self.nodes.push(child.clone())
+                    }
+                }
+            },
+            ChildrenType::Leafs(leafs) => {
+                for leaf in leafs.iter().rev() {
+                    if leaf.borrow().mbr().intersects(&self.query) {
+                        self.leafs.push(leaf.clone())
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: Scalar> Iterator for WindowQuery<S> {
+    type Item = TreeGeometry<S>;
+
+    fn next(&mut self) -> Option<TreeGeometry<S>> {
+        loop {
+            if let Some(leaf) = self.leafs.pop() {
+                self.remaining = self.remaining.saturating_sub(1);
+                return Some(leaf)
+            }
+            let node = self.nodes.pop()?;
+            self.expand(&node.borrow());
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.leafs.len(), Some(self.remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::grid_tree;
+
+    fn leaf_node(geoms: Vec<Geometry>) -> RtreeNode {
+        let geoms: Vec<TreeGeometry> = geoms.into_iter()
+            .map(RtreeGeometry::new_shared)
+            .collect();
+        let mbrs: Vec<BoundingRectangle> = geoms.iter()
+            .map(|geom| geom.borrow().mbr)
+            .collect();
+        let mut node = RtreeNode::new(
+            BoundingRectangle::common_mbr(&mbrs.iter().collect()),
+            4,
+            ChildrenType::Leafs
+        );
+        for geom in geoms {
+            node.insert(geom);
+        }
+        node
+    }
+
+    #[test]
+    fn test_overlap_enlargement() {
+        let node = leaf_node(vec!(
+            vec!((0, 0), (4, 4)),
+            vec!((0, 6), (3, 10)),
+            vec!((5, 0), (9, 4))
+        ));
+        let new_mbr = BoundingRectangle::new((6, 5), (6, 5));
+
+        let mbrs = node.children.mbrs();
+        let area_enlargement = |index: usize| {
+            mbrs[index].union(&new_mbr).area - mbrs[index].area
+        };
+
+        assert_eq!(area_enlargement(0), 14);
+        assert_eq!(area_enlargement(1), 18);
+
+        assert_eq!(node.overlap_enlargement(0, &new_mbr), 4);
+        assert_eq!(node.overlap_enlargement(1, &new_mbr), 0);
+    }
+
+    #[test]
+    fn test_insert_into_empty_inner_root() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::InnerNodes
+        );
+        root.insert(RtreeGeometry::new_shared(vec!((1, 2), (3, 4))));
+
+        assert!(matches!(root.children, ChildrenType::Leafs(_)));
+        assert_eq!(root.cached_height(), 1);
+        assert_eq!(root.mbr.left, (1, 2));
+        assert_eq!(root.geometries().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "max_children must be at least 2")]
+    fn test_single_max_children() {
+        RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 1, ChildrenType::Leafs);
+    }
+
+    #[test]
+    fn test_cached_height() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for i in 0..60 {
+            let geom = RtreeGeometry::new_shared(vec!((i % 8, i / 8)));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+            assert_eq!(root.cached_height(), root.height());
+        }
+
+        assert_eq!(root.geometries().len(), 60);
+        assert!(root.cached_height() > 2);
+        assert!(root.validate().is_empty());
+
+        for id in &ids[..55] {
+            assert!(root.delete(id).is_some());
+            assert_eq!(root.cached_height(), root.height());
+        }
+
+        assert_eq!(root.geometries().len(), 5);
+        assert_eq!(root.cached_height(), 2);
+        assert!(root.validate().is_empty());
+        assert!(root.delete(&ids[0]).is_none());
+    }
+
+    #[test]
+    fn test_query_window_by_overlap() {
+        let node = leaf_node(vec!(
+            vec!((8, 8), (12, 12)),
+            vec!((3, 3), (6, 6)),
+            vec!((20, 20), (25, 25))
+        ));
+        let query = BoundingRectangle::new((0, 0), (10, 10));
+
+        let ranked = node.query_window_by_overlap(&query);
+
+        assert_eq!(ranked.len(), 2);
+        assert_eq!(ranked[0].0.borrow().mbr.left, (3, 3));
+        assert_eq!(ranked[0].1, 9);
+        assert_eq!(ranked[1].0.borrow().mbr.left, (8, 8));
+        assert_eq!(ranked[1].1, 4);
+    }
+
+    #[test]
+    fn test_drop_deep_tree() {
+        let leaf = RtreeNode::new_shared(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        leaf.borrow_mut().insert(RtreeGeometry::new_shared(vec!((0, 0))));
+        let leaf_ref = Rc::downgrade(&leaf);
+
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::InnerNodes
+        );
+        root.children = ChildrenType::InnerNodes(vec!(leaf));
+        for _ in 0..200_000 {
+            let mut parent = RtreeNode::new(
+                BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::InnerNodes
+            );
+            parent.children = ChildrenType::InnerNodes(vec!(Rc::new(RefCell::new(root))));
+            root = parent;
+        }
+
+        assert!(leaf_ref.upgrade().is_some());
+        drop(root);
+        assert!(leaf_ref.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_scalar_coordinates() {
+        let mut float_root = RtreeNode::new(
+            BoundingRectangle::new((0.0, 0.0), (0.0, 0.0)), 4, ChildrenType::Leafs
+        );
+        let mut int_root: RtreeNode<i32> = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..20 {
+            float_root.insert(RtreeGeometry::new_shared(vec!((i as f64 * 0.5, 0.25))));
+            int_root.insert(RtreeGeometry::new_shared(vec!((i, 1))));
+        }
+
+        let floats = float_root.query_window(&BoundingRectangle::new((1.0, 0.0), (2.0, 1.0)));
+        let ints = int_root.query_window(&BoundingRectangle::new((2, 0), (4, 2)));
+
+        assert_eq!(floats.len(), 3);
+        assert_eq!(ints.len(), 3);
+        assert_eq!(float_root.cached_height(), float_root.height());
+    }
+
+    #[test]
+    fn test_dead_space() {
+        let sparse = leaf_node(vec!(
+            vec!((0, 0), (1, 1)),
+            vec!((9, 9), (10, 10))
+        ));
+        let packed = leaf_node(vec!(
+            vec!((0, 0), (5, 10)),
+            vec!((5, 0), (10, 10))
+        ));
+
+        assert_eq!(sparse.mbr.area, 100);
+        assert_eq!(sparse.dead_space(), 98);
+        assert_eq!(packed.dead_space(), 0);
+    }
+
+    #[test]
+    fn test_saturated_areas() {
+        let full = vec!((0, 0), (i64::MAX, i64::MAX));
+        let node = leaf_node(vec!(vec!((0, 0), (1, 1)), full.clone(), full));
+        let grown = BoundingRectangle::new((i64::MAX - 1, i64::MAX - 1), (i64::MAX, i64::MAX));
+
+        assert_eq!(node.mbr.area, i64::MAX);
+        assert_eq!(node.dead_space(), 0);
+        assert_eq!(node.overlap_enlargement(0, &grown), i64::MAX);
+    }
+
+    #[test]
+    fn test_query_window() {
+        let node = leaf_node(vec!(
+            vec!((0, 0), (2, 2)),
+            vec!((5, 5)),
+            vec!((8, 8), (9, 9))
+        ));
+        let query = BoundingRectangle::new((1, 1), (6, 6));
+
+        let found = node.query_window(&query);
+
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].borrow().mbr.left, (0, 0));
+        assert_eq!(found[1].borrow().mbr.left, (5, 5));
+    }
+
+    #[test]
+    fn test_query_window_mode() {
+        let node = leaf_node(vec!(
+            vec!((6, 2), (9, 4)),
+            vec!((2, 2), (4, 4))
+        ));
+        let query = BoundingRectangle::new((1, 1), (6, 6));
+
+        let inclusive = node.query_window_mode(&query, Boundary::Inclusive);
+        let exclusive = node.query_window_mode(&query, Boundary::Exclusive);
+
+        assert_eq!(inclusive.len(), 2);
+        assert_eq!(exclusive.len(), 1);
+        assert_eq!(exclusive[0].borrow().mbr.left, (2, 2));
+    }
+
+    #[test]
+    fn test_query_window_ranked() {
+        let node = leaf_node(vec!(
+            vec!((0, 0), (2, 2)),
+            vec!((6, 6), (7, 7)),
+            vec!((4, 5)),
+            vec!((20, 20))
+        ));
+        let query = BoundingRectangle::new((0, 0), (10, 10));
+
+        let ranked = node.query_window_ranked(&query);
+
+        assert_eq!(ranked.len(), 3);
+        assert_eq!(ranked[0].0.borrow().mbr.left, (4, 5));
+        assert_eq!(ranked[0].1, 1.0);
+        assert_eq!(ranked[1].0.borrow().mbr.left, (6, 6));
+        assert_eq!(ranked[2].0.borrow().mbr.left, (0, 0));
+        assert!(ranked[1].1 <= ranked[2].1);
+    }
+
+    #[test]
+    fn test_query_window_ranked_odd_query() {
+        let node = leaf_node(vec!(
+            vec!((1, 0)),
+            vec!((2, 2))
+        ));
+        let query = BoundingRectangle::new((0, 0), (3, 3));
+
+        let ranked = node.query_window_ranked(&query);
+
+        assert_eq!(ranked[0].0.borrow().mbr.left, (2, 2));
+        assert_eq!(ranked[1].0.borrow().mbr.left, (1, 0));
+        assert!((ranked[0].1 - 0.5_f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_join_with() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut other = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..30 {
+            root.insert(RtreeGeometry::new_shared(vec!((i, i), (i + 2, i + 2))));
+        }
+        for i in 0..6 {
+            other.insert(RtreeGeometry::new_shared(vec!((i * 5, 0), (i * 5 + 1, 40))));
+        }
+        other.insert(RtreeGeometry::new_shared(vec!((100, 100))));
+
+        let mut expected = 0;
+        for geom in root.geometries() {
+            for other_geom in other.geometries() {
+                if geom.borrow().mbr.intersects(&other_geom.borrow().mbr) {
+                    expected += 1;
+                }
+            }
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        root.join_with(&other, |geom, other_geom| {
+            assert!(seen.insert((geom.borrow().id.clone(), other_geom.borrow().id.clone())));
+        });
+
+        assert!(root.cached_height() > other.cached_height());
+        assert_eq!(seen.len(), expected);
+        assert_eq!(other.join(&root).len(), expected);
+    }
+
+    #[test]
+    fn test_query_enclosing() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..5 {
+            root.insert(RtreeGeometry::new_shared(vec!((10 - i * 2, 10 - i * 2), (10 + i * 2, 10 + i * 2))));
+        }
+        for i in 0..10 {
+            root.insert(RtreeGeometry::new_shared(vec!((30 + i, 30), (31 + i, 31))));
+        }
+        let query = BoundingRectangle::new((7, 8), (12, 12));
+
+        let found = root.query_enclosing(&query);
+
+        assert_eq!(found.len(), 3);
+        for geom in found {
+            assert!(geom.borrow().mbr.left.0 <= 6);
+        }
+        assert_eq!(root.query_enclosing(&BoundingRectangle::new((10, 10), (10, 10))).len(), 5);
+    }
+
+    #[test]
+    fn test_get() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for i in 0..20 {
+            let geom = RtreeGeometry::new_shared(vec!((i, 2 * i)));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+        }
+
+        let found = root.get(&ids[13]).unwrap();
+
+        assert_eq!(found.borrow().coords, vec!((13, 26)));
+        assert!(root.contains_id(&ids[13]));
+        assert!(root.get("missing").is_none());
+        assert!(!root.contains_id("missing"));
+    }
+
+    #[test]
+    fn test_set_coords() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for i in 0..20 {
+            let geom = RtreeGeometry::new_shared(vec!((i % 5, i / 5)));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+        }
+        let geom = root.get(&ids[6]).unwrap();
+        let leaf_mbr = root.leaf_mbr_of(&ids[6]).unwrap();
+        let inside = leaf_mbr.left;
+
+        assert_eq!(root.set_coords(&ids[6], vec!(inside)), Ok(()));
+        assert_eq!(root.leaf_mbr_of(&ids[6]).unwrap().left, leaf_mbr.left);
+        assert_eq!(geom.borrow().coords, vec!(inside));
+        assert!(root.validate().is_empty());
+
+        assert_eq!(root.set_coords(&ids[6], vec!((50, 50), (52, 53))), Ok(()));
+        let found = root.query_window(&BoundingRectangle::new((49, 49), (60, 60)));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].borrow().id, ids[6]);
+        assert_eq!(found[0].borrow().coordtype, GeometryType::Line);
+        assert!(Rc::ptr_eq(&found[0], &geom));
+        assert!(root.leaf_mbr_of(&ids[6]).unwrap().contains(&found[0].borrow().mbr));
+        assert_eq!(root.geometries().len(), 20);
+        assert!(root.validate().is_empty());
+
+        let moved = BoundingRectangle::new((9, 9), (9, 9));
+        let neighbours: Vec<&String> = ids.iter()
+            .filter(|id| {
+                let mbr = root.leaf_mbr_of(id).unwrap();
+                *id != &ids[0] && mbr.contains(&BoundingRectangle::new((0, 0), (0, 0)))
+            })
+            .collect();
+        assert_eq!(root.set_coords(&ids[0], vec!((9, 9))), Ok(()));
+        for id in neighbours {
+            assert!(!root.leaf_mbr_of(id).unwrap().contains(&moved));
+        }
+        assert!(root.leaf_mbr_of(&ids[0]).unwrap().contains(&moved));
+        assert!(root.validate().is_empty());
+
+        let mut diagonal = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut last = String::new();
+        for i in 0..20 {
+            last = diagonal.insert_point((i, i));
+        }
+        assert_eq!(diagonal.set_coords(&last, vec!((18, 18))), Ok(()));
+        assert_eq!(diagonal.mbr.right, (18, 18));
+        assert!(diagonal.validate().is_empty());
+
+        assert_eq!(root.set_coords(&ids[6], vec!()), Err(RtreeError::EmptyGeometry));
+        assert_eq!(
+            root.set_coords("missing", vec!((1, 1))),
+            Err(RtreeError::UnknownId(String::from("missing")))
+        );
+    }
+
+    #[test]
+    fn test_into_geometries() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..25 {
+            root.insert(RtreeGeometry::new_shared(vec!((i, i), (i + 1, i))));
+        }
+        let shared = root.geometries()[0].clone();
+
+        let geoms = root.into_geometries();
+
+        assert_eq!(geoms.len(), 25);
+        assert!(geoms.iter().any(|geom| geom.id == shared.borrow().id));
+        assert_eq!(Rc::strong_count(&shared), 1);
+    }
+
+    #[test]
+    fn test_query_window_cornered() {
+        let node = leaf_node(vec!(
+            vec!((1, 1), (2, 2)),
+            vec!((8, 1), (9, 2)),
+            vec!((1, 8), (2, 9)),
+            vec!((8, 8), (9, 9))
+        ));
+        let query = BoundingRectangle::new((0, 0), (10, 10));
+
+        let cornered = node.query_window_cornered(&query);
+        let corner_at = |left: Coordinates<i64>| {
+            cornered.iter().find(|(geom, _)| geom.borrow().mbr.left == left).unwrap().1
+        };
+
+        assert_eq!(cornered.len(), 4);
+        assert_eq!(corner_at((1, 1)), Corner::BottomLeft);
+        assert_eq!(corner_at((8, 1)), Corner::BottomRight);
+        assert_eq!(corner_at((1, 8)), Corner::TopLeft);
+        assert_eq!(corner_at((8, 8)), Corner::TopRight);
+    }
+
+    #[test]
+    fn test_query_window_cornered_odd_query() {
+        let node = leaf_node(vec!(
+            vec!((1, 0)),
+            vec!((2, 2)),
+            vec!((1, 3)),
+            vec!((2, 3))
+        ));
+        let query = BoundingRectangle::new((0, 0), (3, 5));
+
+        let cornered = node.query_window_cornered(&query);
+        let corner_at = |left: Coordinates<i64>| {
+            cornered.iter().find(|(geom, _)| geom.borrow().mbr.left == left).unwrap().1
+        };
+
+        assert_eq!(corner_at((1, 0)), Corner::BottomLeft);
+        assert_eq!(corner_at((2, 2)), Corner::BottomRight);
+        assert_eq!(corner_at((1, 3)), Corner::TopLeft);
+        assert_eq!(corner_at((2, 3)), Corner::TopRight);
+    }
+
+    #[test]
+    fn test_kth_nearest_distance() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..40 {
+            root.insert(RtreeGeometry::new_shared(vec!((i * 7 % 31, i * 3 % 17))));
+        }
+        let point = (12, 9);
+
+        let nearest = root.nearest_k(point, 5);
+        let mut expected: Vec<f64> = root.geometries().iter()
+            .map(|geom| geom.borrow().mbr.min_dist(point))
+            .collect();
+        expected.sort_by(|a, b| a.total_cmp(b));
+
+        assert_eq!(nearest.len(), 5);
+        for (index, (geom, distance)) in nearest.iter().enumerate() {
+            assert_eq!(*distance, expected[index]);
+            assert_eq!(geom.borrow().mbr.min_dist(point), *distance);
+        }
+        assert_eq!(root.kth_nearest_distance(point, 5), Some(nearest[4].1));
+        assert_eq!(root.kth_nearest_distance(point, 40), Some(expected[39]));
+        assert_eq!(root.kth_nearest_distance(point, 41), None);
+    }
+
+    #[test]
+    fn test_neighbors_of() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let center = RtreeGeometry::new_shared(vec!((10, 10), (20, 20)));
+        let center_id = center.borrow().id.clone();
+        let far = RtreeGeometry::new_shared(vec!((100, 100), (110, 110)));
+        let far_id = far.borrow().id.clone();
+        root.insert(center);
+        root.insert(far);
+        for (x, y) in [(8, 8), (18, 8), (18, 18), (8, 18), (14, 14)] {
+            root.insert(RtreeGeometry::new_shared(vec!((x, y), (x + 3, y + 3))));
+        }
+
+        let neighbors = root.neighbors_of(&center_id);
+
+        assert_eq!(neighbors.len(), 5);
+        assert!(neighbors.iter().all(|geom| geom.borrow().id != center_id));
+        assert!(root.neighbors_of(&far_id).is_empty());
+        assert!(root.neighbors_of("missing").is_empty());
+    }
+
+    #[test]
+    fn test_walk() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..50 {
+            root.insert(RtreeGeometry::new_shared(vec!((i % 10, i / 10))));
+        }
+        let height = root.cached_height();
+
+        let mut visited = 0;
+        let mut max_depth = 0;
+        root.walk(|_, depth| {
+            visited += 1;
+            max_depth = cmp::max(max_depth, depth);
+            ControlFlow::Continue(())
+        });
+
+        let (pruned_id, subtree_size) = match &root.children {
+            ChildrenType::InnerNodes(nodes) => {
+                let mut size = 0;
+                nodes[0].borrow().walk(|_, _| {
+                    size += 1;
+                    ControlFlow::Continue(())
+                });
+                (nodes[0].borrow().id.clone(), size)
+            },
+            ChildrenType::Leafs(_) => unreachable!()
+        };
+        let mut pruned_visited = 0;
+        root.walk(|node, _| {
+            pruned_visited += 1;
+            if node.id == pruned_id {
+                ControlFlow::Break(Walk::Prune)
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        let mut root_only = 0;
+        root.walk(|_, _| {
+            root_only += 1;
+            ControlFlow::Break(Walk::Prune)
+        });
+
+        let mut stopped_visited = 0;
+        let mut after_stop = 0;
+        let mut stopped = false;
+        root.walk(|node, _| {
+            if stopped {
+                after_stop += 1;
+            }
+            stopped_visited += 1;
+            if node.id == pruned_id {
+                stopped = true;
+                ControlFlow::Break(Walk::Stop)
+            } else {
+                ControlFlow::Continue(())
+            }
+        });
+
+        assert_eq!(max_depth, height - 1);
+        assert!(subtree_size > 1);
+        assert_eq!(pruned_visited, visited - (subtree_size - 1));
+        assert_eq!(root_only, 1);
+        assert!(stopped);
+        assert_eq!(after_stop, 0);
+        assert_eq!(stopped_visited, 2);
+    }
+
+    #[test]
+    fn test_reverse_nearest() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 2, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for x in [0, 2, 3, 10, 20] {
+            let geom = RtreeGeometry::new_shared(vec!((x, 0)));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+        }
+        let rnn = |index: usize| {
+            let mut xs: Vec<i64> = root.reverse_nearest(&ids[index]).iter()
+                .map(|geom| geom.borrow().mbr.left.0)
+                .collect();
+            xs.sort();
+            xs
+        };
+
+        assert_eq!(rnn(0), Vec::<i64>::new());
+        assert_eq!(rnn(1), vec!(0, 3));
+        assert_eq!(rnn(2), vec!(2, 10));
+        assert_eq!(rnn(3), vec!(20));
+        assert_eq!(rnn(4), Vec::<i64>::new());
+        assert!(root.reverse_nearest("missing").is_empty());
+    }
+
+    #[test]
+    fn test_path_to() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for i in 0..40 {
+            let geom = RtreeGeometry::new_shared(vec!((i % 7, i / 7)));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+        }
+
+        let path = root.path_to(&ids[17]).unwrap();
+
+        assert_eq!(path.len(), root.height());
+        assert_eq!(path[0], root.id);
+        assert!(root.path_to("missing").is_none());
+    }
+
+    fn tiles(count: i64, side: i64) -> Vec<BoundingRectangle> {
+        let mut tiles = vec!();
+        for x in 0..count {
+            for y in 0..count {
+                tiles.push(BoundingRectangle::new((x * side, y * side), ((x + 1) * side, (y + 1) * side)));
+            }
+        }
+        tiles
+    }
+
+    #[test]
+    fn test_query_windows() {
+        let root = grid_tree(20);
+        let queries = tiles(4, 15);
+
+        let batched = root.query_windows(&queries);
+
+        assert_eq!(batched.len(), queries.len());
+        for (query, found) in queries.iter().zip(batched.iter()) {
+            let mut expected: Vec<String> = root.query_window(query).iter()
+                .map(|geom| geom.borrow().id.clone())
+                .collect();
+            let mut found: Vec<String> = found.iter()
+                .map(|geom| geom.borrow().id.clone())
+                .collect();
+            expected.sort();
+            found.sort();
+            assert!(!found.is_empty());
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_query_windows() {
+        let root = grid_tree(200);
+        let queries = tiles(20, 30);
+
+        let start = std::time::Instant::now();
+        for _ in 0..20 {
+            root.query_windows(&queries);
+        }
+        let batched = start.elapsed();
+
+        let start = std::time::Instant::now();
+        for _ in 0..20 {
+            for query in &queries {
+                root.query_window(query);
+            }
+        }
+        let independent = start.elapsed();
+
+        println!("batched: {:?}, independent: {:?}", batched, independent);
+    }
+
+    #[test]
+    fn test_query_window_bucketed() {
+        let root = grid_tree(10);
+        let query = BoundingRectangle::new((0, 0), (14, 8));
+
+        let buckets = root.query_window_bucketed(&query, |center| (center.0 / 10, center.1 / 10));
+
+        let total: usize = buckets.values().map(|geoms| geoms.len()).sum();
+        assert_eq!(total, root.query_window(&query).len());
+        assert_eq!(buckets.len(), 2);
+        for (key, geoms) in &buckets {
+            for geom in geoms {
+                let center = geom.borrow().mbr.center();
+                assert_eq!((center.0 / 10, center.1 / 10), *key);
+            }
+        }
+        assert_eq!(buckets[&(0, 0)].len(), 9);
+        assert_eq!(buckets[&(1, 0)].len(), 6);
+    }
+
+    #[test]
+    fn test_propagate_mbr() {
+        let mut root = grid_tree(8);
+        let id = root.geometries()[5].borrow().id.clone();
+        let added = BoundingRectangle::new((40, 40), (45, 47));
+
+        root.get(&id).unwrap().borrow_mut().set_coords(vec!((40, 40), (45, 47))).unwrap();
+        root.propagate_mbr(&id, &added);
+
+        let path = root.path_to(&id).unwrap();
+        let mut checked = 0;
+        root.walk(|node, _| {
+            if path.contains(&node.id) {
+                assert!(node.mbr.contains(&added));
+                checked += 1;
+            }
+            ControlFlow::Continue(())
+        });
+        assert_eq!(checked, path.len());
+
+        let found = root.query_window(&BoundingRectangle::new((41, 41), (42, 42)));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].borrow().id, id);
+
+        let before = root.mbr;
+        root.propagate_mbr(&id, &BoundingRectangle::new((1, 1), (2, 2)));
+        assert_eq!((root.mbr.left, root.mbr.right), (before.left, before.right));
+    }
+
+    #[test]
+    fn test_query_window_limited() {
+        let root = grid_tree(20);
+        let dense = BoundingRectangle::new((0, 0), (59, 59));
+        let sparse = BoundingRectangle::new((0, 0), (4, 1));
+
+        let limited = root.query_window_limited(&dense, 25);
+        assert_eq!(limited.len(), 25);
+        for geom in &limited {
+            assert!(geom.borrow().mbr.intersects(&dense));
+        }
+
+        assert_eq!(root.query_window_limited(&sparse, 25).len(), 2);
+        assert!(root.query_window_limited(&dense, 0).is_empty());
+    }
+
+    #[test]
+    fn test_geometry_getters() {
+        let mut root = grid_tree(4);
+        root.insert(RtreeGeometry::new_shared(vec!((20, 20), (24, 20), (22, 23), (20, 20))));
+
+        let found = root.query_window(&BoundingRectangle::new((21, 21), (22, 22)));
+
+        assert_eq!(found.len(), 1);
+        let geom = found[0].borrow();
+        assert_eq!(geom.coords(), &vec!((20, 20), (24, 20), (22, 23), (20, 20)));
+        assert_eq!(geom.geometry_type(), &GeometryType::Polygon);
+    }
+
+    #[test]
+    fn test_query_obb() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let on_strip = RtreeGeometry::new_shared(vec!((5, 5), (6, 6)));
+        let off_strip = RtreeGeometry::new_shared(vec!((5, -5), (6, -4)));
+        let far = RtreeGeometry::new_shared(vec!((30, 30)));
+        let on_id = on_strip.borrow().id.clone();
+        root.insert(on_strip);
+        root.insert(off_strip.clone());
+        root.insert(far);
+
+        let angle = std::f64::consts::FRAC_PI_4;
+        let found = root.query_obb((0, 0), (10, 1), angle);
+
+        assert!(off_strip.borrow().mbr.intersects(&BoundingRectangle::new((-8, -8), (8, 8))));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].borrow().id, on_id);
+        assert_eq!(root.query_obb((0, 0), (10, 1), 0.0).len(), 0);
+    }
+
+    #[test]
+    fn test_nearest_to_geometry() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let query = RtreeGeometry::new_shared(vec!((0, 0), (10, 10)));
+        for offset in 0..6 {
+            let left = 2 + offset * 5;
+            root.insert(RtreeGeometry::new_shared(vec!(
+                (left, -20), (left + 3, -20), (left + 3, -10 - offset), (left, -20)
+            )));
+        }
+        let corner = RtreeGeometry::new_shared(vec!((9, 1), (12, 1), (12, 4), (9, 1)));
+        let decoy = RtreeGeometry::new_shared(vec!((11, 0), (20, 0), (20, 9), (11, 0)));
+        let corner_id = corner.borrow().id.clone();
+        root.insert(corner);
+        root.insert(decoy);
+        root.insert(query.clone());
+
+        let (nearest, distance) = root.nearest_to_geometry(&query.borrow()).unwrap();
+
+        assert_eq!(nearest.borrow().id, corner_id);
+        assert!((distance - 32.0_f64.sqrt()).abs() < 1e-9);
+
+        let empty = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs);
+        assert!(empty.nearest_to_geometry(&query.borrow()).is_none());
+    }
+
+    #[test]
+    fn test_reserve() {
+        let mut root = leaf_node(vec!(vec!((1, 1)), vec!((2, 2))));
+        root.reserve(100);
+
+        assert!(root.children.capacity() >= 102);
+        assert_eq!(root.children.len(), 2);
+    }
+
+    #[test]
+    fn test_iter_window_owned() {
+        let mut root = grid_tree(10);
+        let query = BoundingRectangle::new((4, 4), (16, 11));
+
+        let mut expected: Vec<Geometry> = root.query_window(&query).iter()
+            .map(|geom| geom.borrow().coords.clone())
+            .collect();
+        let mut owned: Vec<Geometry> = root.iter_window_owned(query).collect();
+        root.insert(RtreeGeometry::new_shared(vec!((5, 5))));
+        expected.sort();
+        owned.sort();
+
+        assert!(!owned.is_empty());
+        assert_eq!(owned, expected);
+    }
+
+    #[test]
+    fn test_preview_split() {
+        let node = leaf_node(vec!(
+            vec!((0, 0), (1, 1)),
+            vec!((20, 20), (22, 21)),
+            vec!((2, 1), (3, 2)),
+            vec!((18, 19), (20, 20))
+        ));
+        let ids: Vec<String> = node.geometries().iter().map(|geom| geom.borrow().id.clone()).collect();
+
+        let (mbr_1, mbr_2, count_1, count_2) = node.preview_split().unwrap();
+        let union = mbr_1.union(&mbr_2);
+
+        assert_eq!((union.left, union.right), (node.mbr.left, node.mbr.right));
+        assert_eq!((count_1, count_2), (2, 2));
+        assert_eq!(node.children.len(), 4);
+        let after: Vec<String> = node.geometries().iter().map(|geom| geom.borrow().id.clone()).collect();
+        assert_eq!(after, ids);
+        assert!(leaf_node(vec!(vec!((1, 1)))).preview_split().is_none());
+    }
+
+    #[test]
+    fn test_insert_enlarges_root() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((5, 6), (5, 6)), 4, ChildrenType::InnerNodes
+        );
+        let node = RtreeNode::new_shared(BoundingRectangle::new((5, 6), (5, 6)), 4, ChildrenType::Leafs);
+        node.borrow_mut().children = ChildrenType::Leafs(vec!(RtreeGeometry::new_shared(vec!((5, 6)))));
+        root.children = ChildrenType::InnerNodes(vec!(node));
+        let outside = RtreeGeometry::new_shared(vec!((1, 2), (3, 4), (1, 2)));
+        let outside_id = outside.borrow().id.clone();
+
+        root.insert(outside);
+
+        assert_eq!((root.mbr.left, root.mbr.right), ((1, 2), (5, 6)));
+        let found = root.query_window(&BoundingRectangle::new((0, 0), (2, 3)));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].borrow().id, outside_id);
+
+        let mut empty = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs);
+        empty.insert(RtreeGeometry::new_shared(vec!((10, 10), (12, 15))));
+        assert_eq!((empty.mbr.left, empty.mbr.right), ((10, 10), (12, 15)));
+    }
+
+    #[test]
+    fn test_type_counts() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..7 {
+            root.insert(RtreeGeometry::new_shared(vec!((i, i))));
+        }
+        for i in 0..4 {
+            root.insert(RtreeGeometry::new_shared(vec!((i, 0), (i + 2, 3))));
+        }
+        for i in 0..2 {
+            root.insert(RtreeGeometry::new_shared(vec!((i, 0), (i + 2, 0), (i + 1, 2), (i, 0))));
+        }
+
+        assert_eq!(root.type_counts(), (7, 4, 2));
+    }
+
+    #[test]
+    fn test_find_point() {
+        let mut root = grid_tree(5);
+        let marker = RtreeGeometry::new_shared(vec!((4, 4)));
+        let coincident = RtreeGeometry::new_shared(vec!((4, 4)));
+        let through = RtreeGeometry::new_shared(vec!((0, 0), (8, 8)));
+        let near = RtreeGeometry::new_shared(vec!((4, 5)));
+        let mut expected = vec!(marker.borrow().id.clone(), coincident.borrow().id.clone());
+        root.insert(marker);
+        root.insert(coincident);
+        root.insert(through);
+        root.insert(near);
+
+        let mut found: Vec<String> = root.find_point((4, 4)).iter()
+            .map(|geom| geom.borrow().id.clone())
+            .collect();
+        found.sort();
+        expected.sort();
+
+        assert!(root.query_window(&BoundingRectangle::new((4, 4), (4, 4))).len() > 2);
+        assert_eq!(found, expected);
+        assert!(root.find_point((100, 100)).is_empty());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn test_sample() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let large = RtreeGeometry::new_shared(vec!((0, 0), (9, 1)));
+        let small = RtreeGeometry::new_shared(vec!((20, 20), (21, 21)));
+        let large_id = large.borrow().id.clone();
+        root.insert(large);
+        root.insert(small);
+        root.insert(RtreeGeometry::new_shared(vec!((5, 5))));
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let sampled = root.sample(10000, &mut rng);
+        let large_count = sampled.iter().filter(|geom| geom.borrow().id == large_id).count();
+        let ratio = large_count as f64 / (sampled.len() - large_count) as f64;
+
+        assert_eq!(sampled.len(), 10000);
+        assert!(ratio > 8.0 && ratio < 10.0, "ratio {}", ratio);
+    }
+
+    #[test]
+    fn test_insert_instrumented() {
+        let mut root = grid_tree(10);
+        let depth = root.height() - 1;
+        let geom = RtreeGeometry::new_shared(vec!((13, 13), (14, 14)));
+        let id = geom.borrow().id.clone();
+        let mut trace = vec!();
+
+        root.insert_instrumented(geom, &mut trace);
+
+        assert!(depth > 0);
+        assert_eq!(trace.len(), depth);
+        let path = root.path_to(&id).unwrap();
+        assert_eq!(path.last(), trace.last());
+
+        let mut leaf_root = leaf_node(vec!(vec!((1, 1))));
+        let mut leaf_trace = vec!();
+        leaf_root.insert_instrumented(RtreeGeometry::new_shared(vec!((2, 2))), &mut leaf_trace);
+        assert!(leaf_trace.is_empty());
+    }
+
+    #[test]
+    fn test_window_query_size_hint() {
+        let root = grid_tree(10);
+        let mut window = root.iter_window(BoundingRectangle::new((4, 4), (16, 11)));
+
+        let (lower, upper) = window.size_hint();
+        assert!(upper.unwrap() >= root.geometry_count());
+
+        let mut yielded = 0;
+        while window.next().is_some() {
+            yielded += 1;
+            let (lower, upper) = window.size_hint();
+            assert!(upper.unwrap() >= lower);
+        }
+
+        assert!(lower <= yielded);
+        assert!(upper.unwrap() >= yielded);
+        assert_eq!(yielded, root.query_window(&BoundingRectangle::new((4, 4), (16, 11))).len());
+        assert_eq!(root.geometry_count(), 100);
+
+        let mut wide = RtreeNode::new(BoundingRectangle::new((0, 0), (9, 0)), 4, ChildrenType::Leafs);
+        for x in 0..10 {
+            wide.children.add_leaf(RtreeGeometry::new_shared(vec!((x, 0))));
+        }
+        let window = wide.iter_window(wide.mbr);
+        let (lower, upper) = window.size_hint();
+        assert_eq!((lower, upper), (10, Some(10)));
+        assert_eq!(window.count(), 10);
+    }
+
+    #[test]
+    fn test_hot_nodes() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (60, 60)), 4, ChildrenType::InnerNodes
+        );
+        let crowded_1 = RtreeNode::new_shared(BoundingRectangle::new((0, 0), (10, 10)), 4, ChildrenType::Leafs);
+        let crowded_2 = RtreeNode::new_shared(BoundingRectangle::new((1, 1), (11, 11)), 4, ChildrenType::Leafs);
+        let clean = RtreeNode::new_shared(BoundingRectangle::new((50, 50), (60, 60)), 4, ChildrenType::Leafs);
+        let mut expected = vec!(crowded_1.borrow().id.clone(), crowded_2.borrow().id.clone());
+        root.children = ChildrenType::InnerNodes(vec!(crowded_1, crowded_2, clean));
+
+        let mut hot = root.hot_nodes(0.5);
+        hot.sort();
+        expected.sort();
+
+        assert_eq!(hot, expected);
+        assert!(root.hot_nodes(0.9).is_empty());
+    }
+
+    #[test]
+    fn test_split_separation() {
+        let clustered = leaf_node(vec!(
+            vec!((0, 0), (2, 2)),
+            vec!((1, 1), (3, 3)),
+            vec!((90, 90), (92, 92)),
+            vec!((91, 91), (93, 93))
+        ));
+        let uniform = leaf_node(vec!(
+            vec!((0, 0), (20, 20)),
+            vec!((25, 25), (45, 45)),
+            vec!((50, 50), (70, 70)),
+            vec!((75, 75), (95, 95))
+        ));
+
+        let clustered = clustered.split_separation().unwrap();
+        let uniform = uniform.split_separation().unwrap();
+
+        assert!(clustered > uniform, "{} <= {}", clustered, uniform);
+        assert!(clustered > 0.9 && clustered <= 1.0);
+        assert!(leaf_node(vec!(vec!((1, 1)))).split_separation().is_none());
+    }
+
+    #[test]
+    fn test_query_window_by_leaf() {
+        let root = grid_tree(10);
+        let query = BoundingRectangle::new((4, 4), (20, 13));
+
+        let groups = root.query_window_by_leaf(&query);
+        let mut expected: Vec<String> = root.query_window(&query).iter()
+            .map(|geom| geom.borrow().id.clone())
+            .collect();
+        let mut grouped = vec!();
+        for (leaf_id, geoms) in &groups {
+            assert!(!geoms.is_empty());
+            for geom in geoms {
+                let path = root.path_to(&geom.borrow().id).unwrap();
+                assert_eq!(path.last(), Some(leaf_id));
+                grouped.push(geom.borrow().id.clone());
+            }
+        }
+        expected.sort();
+        grouped.sort();
+
+        assert!(groups.len() > 1);
+        assert_eq!(grouped, expected);
+    }
+
+    #[test]
+    fn test_extreme_coordinates() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let corner = RtreeGeometry::new_shared(vec!((i64::MAX, i64::MAX)));
+        let corner_id = corner.borrow().id.clone();
+        root.insert(corner);
+        root.insert(RtreeGeometry::new_shared(vec!((i64::MAX - 5, i64::MAX - 3), (i64::MAX - 1, i64::MAX - 2))));
+
+        let found = root.query_window(&BoundingRectangle::new(
+            (i64::MAX - 1, i64::MAX - 1), (i64::MAX, i64::MAX)
+        ));
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].borrow().id, corner_id);
+        assert_eq!(root.mbr.right, (i64::MAX, i64::MAX));
+    }
+
+    #[test]
+    fn test_mixed_extreme_coordinates() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mut ids = vec!();
+        for i in 0..12 {
+            let coord = if i % 2 == 0 { (i, i) } else { (i64::MAX - i + 1, i64::MAX - i + 1) };
+            let geom = RtreeGeometry::new_shared(vec!(coord));
+            ids.push(geom.borrow().id.clone());
+            root.insert(geom);
+        }
+
+        assert_eq!(root.mbr.left, (0, 0));
+        assert_eq!(root.mbr.right, (i64::MAX, i64::MAX));
+        assert_eq!(root.query_window(&BoundingRectangle::new((0, 0), (20, 20))).len(), 6);
+        assert_eq!(root.query_window(&root.mbr.clone()).len(), 12);
+        assert!(root.delete(&ids[1]).is_some());
+        assert_eq!(root.geometries().len(), 11);
+    }
+
+    #[test]
+    fn test_write_wkt() {
+        let mut root = grid_tree(6);
+        root.insert(RtreeGeometry::new_shared(vec!((3, 3))));
+        root.insert(RtreeGeometry::new_shared(vec!((0, 0), (4, 0), (2, 3), (0, 0))));
+        let mut buffer: Vec<u8> = vec!();
+
+        let written = root.write_wkt(&mut buffer).unwrap();
+
+        let text = String::from_utf8(buffer).unwrap();
+        let mut parsed = 0;
+        for line in text.lines() {
+            let open = line.rfind('(').unwrap();
+            let close = line.find(')').unwrap();
+            let coords: Vec<(i64, i64)> = line[open + 1..close].split(", ")
+                .map(|pair| {
+                    let mut values = pair.split(' ').map(|value| value.parse::<i64>().unwrap());
+                    (values.next().unwrap(), values.next().unwrap())
+                })
+                .collect();
+            assert!(!coords.is_empty());
+            parsed += 1;
+        }
+        assert_eq!(written, 38);
+        assert_eq!(parsed, written);
+        assert_eq!(text.lines().filter(|line| line.starts_with("POLYGON")).count(), 1);
+    }
+
+    #[test]
+    fn test_write_wkt_error() {
+        struct FailingWriter {
+            calls: usize
+        }
+
+        impl Write for FailingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.calls += 1;
+                if self.calls == 2 {
+                    Err(io::Error::other("disk full"))
+                } else {
+                    Ok(buf.len())
+                }
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        for i in 0..30 {
+            root.insert_point((i, i));
+        }
+
+        let written = root.write_wkt(FailingWriter { calls: 0 });
+
+        assert_eq!(written.unwrap_err().kind(), io::ErrorKind::Other);
+    }
+
+    #[test]
+    #[should_panic(expected = "tile_size must be greater than zero")]
+    fn test_assign_to_zero_tiles() {
+        grid_tree(2).assign_to_tiles(0);
+    }
+
+    #[test]
+    fn test_assign_to_tiles() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (0, 0)), 4, ChildrenType::Leafs
+        );
+        let mostly_left = RtreeGeometry::new_shared(vec!((4, 2), (12, 4)));
+        let mostly_right = RtreeGeometry::new_shared(vec!((8, 2), (16, 4)));
+        let negative = RtreeGeometry::new_shared(vec!((-6, -3), (-2, -1)));
+        let left_id = mostly_left.borrow().id.clone();
+        let right_id = mostly_right.borrow().id.clone();
+        let negative_id = negative.borrow().id.clone();
+        root.insert(mostly_left);
+        root.insert(mostly_right);
+        root.insert(negative);
+        for i in 0..6 {
+            root.insert(RtreeGeometry::new_shared(vec!((i * 7, 25))));
+        }
+
+        let tiles = root.assign_to_tiles(10);
+
+        let total: usize = tiles.values().map(|geoms| geoms.len()).sum();
+        assert_eq!(total, 9);
+        let ids_in = |tile: (i64, i64)| -> Vec<String> {
+            tiles[&tile].iter().map(|geom| geom.borrow().id.clone()).collect()
+        };
+        assert_eq!(ids_in((0, 0)), vec!(left_id));
+        assert_eq!(ids_in((1, 0)), vec!(right_id));
+        assert_eq!(ids_in((-1, -1)), vec!(negative_id));
+        assert_eq!(tiles[&(0, 2)].len(), 2);
+    }
+
+    #[test]
+    fn test_insert_point() {
+        let mut fast = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 6, ChildrenType::Leafs);
+        let mut plain = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 6, ChildrenType::Leafs);
+        for i in 0..200 {
+            let coord = ((i * 37) % 101, (i * 53) % 97);
+            let id = fast.insert_point(coord);
+            assert_eq!(fast.get(&id).unwrap().borrow().coordtype, GeometryType::Point);
+            plain.insert(RtreeGeometry::new_shared(vec!(coord)));
+        }
+
+        for query in tiles(4, 30) {
+            let mut fast_found: Vec<Geometry> = fast.query_window(&query).iter()
+                .map(|geom| geom.borrow().coords.clone())
+                .collect();
+            let mut plain_found: Vec<Geometry> = plain.query_window(&query).iter()
+                .map(|geom| geom.borrow().coords.clone())
+                .collect();
+            fast_found.sort();
+            plain_found.sort();
+            assert_eq!(fast_found, plain_found);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_insert_point() {
+        let coords: Vec<(i64, i64)> = (0..50000).map(|i| ((i * 7919) % 10007, (i * 104729) % 10009)).collect();
+
+        let start = std::time::Instant::now();
+        let mut plain = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 8, ChildrenType::Leafs);
+        for coord in &coords {
+            plain.insert(RtreeGeometry::new_shared(vec!(*coord)));
+        }
+        let generic = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let mut fast = RtreeNode::new(BoundingRectangle::new((0, 0), (0, 0)), 8, ChildrenType::Leafs);
+        for coord in &coords {
+            fast.insert_point(*coord);
+        }
+        let points = start.elapsed();
+
+        println!("insert: {:?}, insert_point: {:?}", generic, points);
+    }
+
+    #[test]
+    fn test_query_window_buffered() {
+        let mut root = grid_tree(4);
+        let near_miss = RtreeGeometry::new_shared(vec!((32, 20), (34, 22)));
+        let near_id = near_miss.borrow().id.clone();
+        root.insert(near_miss);
+        root.insert(RtreeGeometry::new_shared(vec!((40, 20))));
+        let query = BoundingRectangle::new((20, 15), (30, 25));
+
+        let plain = root.query_window(&query);
+        let buffered = root.query_window_buffered(&query, 3);
+
+        assert!(plain.is_empty());
+        assert_eq!(buffered.len(), 1);
+        assert_eq!(buffered[0].borrow().id, near_id);
+    }
+
+    #[test]
+    fn test_repair() {
+        let mut root = grid_tree(8);
+        assert!(root.validate().is_empty());
+        assert!(grid_tree(10).validate().is_empty());
+
+        let inflated = match &root.children {
+            ChildrenType::InnerNodes(nodes) => nodes[0].clone(),
+            ChildrenType::Leafs(_) => panic!("expected an inner root")
+        };
+        let inflated_id = inflated.borrow().id.clone();
+        let tight = inflated.borrow().mbr;
+        inflated.borrow_mut().mbr = tight.buffer(50);
+        let orphan = match &inflated.borrow().children {
+            ChildrenType::InnerNodes(nodes) => nodes[0].clone(),
+            ChildrenType::Leafs(_) => panic!("expected an inner node")
+        };
+        let orphan_id = orphan.borrow().id.clone();
+        orphan.borrow_mut().parent = None;
+
+        let violations = root.validate();
+        assert!(violations.contains(&Violation::LooseMbr(inflated_id.clone())));
+        assert!(violations.contains(&Violation::MissingParent(orphan_id.clone())));
+
+        let report = root.repair();
+
+        assert!(report.repaired_mbrs.contains(&inflated_id));
+        assert!(report.unfixable.is_empty());
+        assert_eq!(report.relinked_parents, vec!(orphan_id));
+        assert_eq!((inflated.borrow().mbr.left, inflated.borrow().mbr.right), (tight.left, tight.right));
+        assert!(root.validate().is_empty());
+        assert_eq!(root.repair(), RepairReport::default());
+    }
+
+    #[test]
+    fn test_repair_uneven_depth() {
+        let mut root = RtreeNode::new(
+            BoundingRectangle::new((0, 0), (9, 9)), 4, ChildrenType::InnerNodes
+        );
+        let shallow = RtreeNode::new_shared(BoundingRectangle::new((0, 0), (1, 1)), 4, ChildrenType::Leafs);
+        shallow.borrow_mut().children = ChildrenType::Leafs(vec!(RtreeGeometry::new_shared(vec!((0, 0), (1, 1)))));
+        let leaf = RtreeNode::new_shared(BoundingRectangle::new((8, 8), (9, 9)), 4, ChildrenType::Leafs);
+        leaf.borrow_mut().children = ChildrenType::Leafs(vec!(RtreeGeometry::new_shared(vec!((8, 8), (9, 9)))));
+        let deep = RtreeNode::new_shared(BoundingRectangle::new((8, 8), (9, 9)), 4, ChildrenType::InnerNodes);
+        let leaf_id = leaf.borrow().id.clone();
+        deep.borrow_mut().children = ChildrenType::InnerNodes(vec!(leaf));
+        root.children = ChildrenType::InnerNodes(vec!(shallow, deep));
+
+        let report = root.repair();
+
+        assert_eq!(report.unfixable, vec!(Violation::UnevenLeafDepth { id: leaf_id, depth: 2, expected: 1 }));
+        assert_eq!(report.relinked_parents.len(), 3);
     }
 }
@@ -1,63 +1,161 @@
+use std::cell::RefCell;
+use std::mem;
 use std::rc::Rc;
 
-use crate::geometries::{BoundingRectangle, RtreeGeometry};
+use crate::Scalar;
+use crate::geometries::BoundingRectangle;
 use crate::nodes::{ChildrenType, RtreeNode, RtreeObject};
 
-pub trait RtreeSplit {
-    fn split(&mut self) -> ();
-
-    fn execute(&mut self) -> (Rc<RtreeNode>, Rc<RtreeNode>);
-
-    fn pick_seeds<T: RtreeObject>(
-        &mut self,
-        objects: &mut Vec<Rc<T>>, 
-        child_type: fn(Vec<Rc<T>>) -> ChildrenType) -> (Rc<RtreeNode>, Rc<RtreeNode>);
-
-    fn distribute_nodes(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeNode>>
-    ) -> ();
-
-    fn distribute_leafs(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeGeometry>>
-    ) -> ();
-
-    fn pick_next_node(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeNode>>)
-    -> Rc<RtreeNode>;
-
-    fn pick_next_leaf(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeGeometry>>
-    ) -> Rc<RtreeGeometry>;
-
-    fn validate_nodes_quantity(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeNode>>
-    ) -> bool;
-
-    fn validate_leafs_quantity(
-        &self,
-        node_1: &mut Rc<RtreeNode>,
-        node_2: &mut Rc<RtreeNode>,
-        objects: &mut Vec<Rc<RtreeGeometry>>
-    ) -> bool;
-
-    fn pick_underfull<'a>(
-        &'a self,
-        node_1: &'a mut Rc<RtreeNode>,
-        node_2: &'a mut Rc<RtreeNode>
-    ) -> Option<&'a mut Rc<RtreeNode>>;
-}
\ No newline at end of file
+pub type Entries<T> = Vec<Rc<RefCell<T>>>;
+
+pub trait RtreeSplit<S: Scalar = i64> {
+    fn split(&mut self) -> RtreeNode<S>;
+}
+
+impl<S: Scalar> RtreeSplit<S> for RtreeNode<S> {
+    fn split(&mut self) -> RtreeNode<S> {
+        let min_children = self.min_children();
+        let sibling_children = match &mut self.children {
+            ChildrenType::InnerNodes(ref mut nodes) => {
+                let (group_1, group_2) = execute(mem::take(nodes), min_children);
+                *nodes = group_1;
+                ChildrenType::InnerNodes(group_2)
+            },
+            ChildrenType::Leafs(ref mut leafs) => {
+                let (group_1, group_2) = execute(mem::take(leafs), min_children);
+                *leafs = group_1;
+                ChildrenType::Leafs(group_2)
+            }
+        };
+        self.mbr = self.children.common_mbr();
+        RtreeNode::from_children(
+            sibling_children, self.max_children, self.cached_height(), self.ids.clone()
+        )
+    }
+}
+
+fn enlargement<S: Scalar>(mbr: &BoundingRectangle<S>, added: &BoundingRectangle<S>) -> S {
+    mbr.union(added).area - mbr.area
+}
+
+pub fn execute<S: Scalar, T: RtreeObject<S>>(
+    mut objects: Entries<T>,
+    min_children: usize
+) -> (Entries<T>, Entries<T>) {
+
+    let (seed_1, seed_2) = pick_seeds(&objects);
+    let entry_2 = objects.remove(seed_2);
+    let entry_1 = objects.remove(seed_1);
+
+    let mut mbr_1 = *entry_1.borrow().mbr();
+    let mut mbr_2 = *entry_2.borrow().mbr();
+    let mut group_1 = vec!(entry_1);
+    let mut group_2 = vec!(entry_2);
+
+    while !objects.is_empty() {
+        if group_1.len() + objects.len() <= min_children {
+            group_1.append(&mut objects);
+            break
+        }
+        if group_2.len() + objects.len() <= min_children {
+            group_2.append(&mut objects);
+            break
+        }
+
+        let entry = objects.remove(pick_next(&objects, &mbr_1, &mbr_2));
+        let mbr = *entry.borrow().mbr();
+        let enl_1 = enlargement(&mbr_1, &mbr);
+        let enl_2 = enlargement(&mbr_2, &mbr);
+
+        let to_first = if enl_1 != enl_2 {
+            enl_1 < enl_2
+        } else if mbr_1.area != mbr_2.area {
+            mbr_1.area < mbr_2.area
+        } else {
+            group_1.len() <= group_2.len()
+        };
+
+        if to_first {
+            mbr_1 = mbr_1.union(&mbr);
+            group_1.push(entry);
+        } else {
+            mbr_2 = mbr_2.union(&mbr);
+            group_2.push(entry);
+        }
+    }
+
+    (group_1, group_2)
+}
+
+pub fn pick_seeds<S: Scalar, T: RtreeObject<S>>(objects: &[Rc<RefCell<T>>]) -> (usize, usize) {
+    let mut seeds = (0, 1);
+    let mut max_waste = S::min_value();
+
+    for i in 0..objects.len() {
+        for j in (i + 1)..objects.len() {
+            let obj_1 = objects[i].borrow();
+            let obj_2 = objects[j].borrow();
+            let combined = obj_1.mbr().union(obj_2.mbr());
+            let waste = combined.area - obj_1.mbr().area - obj_2.mbr().area;
+
+            if waste > max_waste {
+                max_waste = waste;
+                seeds = (i, j);
+            }
+        }
+    }
+    seeds
+}
+
+fn pick_next<S: Scalar, T: RtreeObject<S>>(
+    objects: &[Rc<RefCell<T>>],
+    mbr_1: &BoundingRectangle<S>,
+    mbr_2: &BoundingRectangle<S>
+) -> usize {
+
+    let mut chosen = 0;
+    let mut max_diff = None;
+
+    for (index, object) in objects.iter().enumerate() {
+        let mbr = *object.borrow().mbr();
+        let enl_1 = enlargement(mbr_1, &mbr);
+        let enl_2 = enlargement(mbr_2, &mbr);
+        let diff = if enl_1 > enl_2 { enl_1 - enl_2 } else { enl_2 - enl_1 };
+        if max_diff.is_none_or(|max| diff > max) {
+            max_diff = Some(diff);
+            chosen = index;
+        }
+    }
+    chosen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometries::RtreeGeometry;
+    use crate::nodes::TreeGeometry;
+
+    #[test]
+    fn test_execute() {
+        let objects: Vec<TreeGeometry> = vec!(
+            RtreeGeometry::new_shared(vec!((0, 0))),
+            RtreeGeometry::new_shared(vec!((20, 20))),
+            RtreeGeometry::new_shared(vec!((1, 1))),
+            RtreeGeometry::new_shared(vec!((19, 19))),
+            RtreeGeometry::new_shared(vec!((2, 0)))
+        );
+
+        assert_eq!(pick_seeds(&objects), (0, 1));
+
+        let (group_1, group_2) = execute(objects, 2);
+
+        assert_eq!(group_1.len(), 3);
+        assert_eq!(group_2.len(), 2);
+        for geom in group_1 {
+            assert!(geom.borrow().mbr.right.0 <= 2);
+        }
+        for geom in group_2 {
+            assert!(geom.borrow().mbr.left.0 >= 19);
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use crate::{Geometry, Scalar};
+use crate::errors::RtreeError;
+use crate::geometries::{BoundingRectangle, RtreeGeometry};
+use crate::nodes::{ChildrenType, RtreeNode, TreeGeometry};
+use crate::utils::{generate_id, IdSource};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IdScheme {
+    Uuid,
+    Sequential
+}
+
+#[derive(Clone, Debug)]
+pub struct RtreeConfig {
+    pub max_children: u8,
+    pub snap_precision: Option<f64>,
+    pub deduplicate: bool,
+    pub id_scheme: IdScheme,
+    pub record_order: bool
+}
+
+pub struct Rtree<S: Scalar = i64> {
+    root: RtreeNode<S>,
+    config: RtreeConfig,
+    len: usize,
+    ids: IdSource,
+    next_sequence: u64
+}
+
+impl Default for RtreeConfig {
+    fn default() -> RtreeConfig {
+        RtreeConfig {
+            max_children: 4,
+            snap_precision: None,
+            deduplicate: false,
+            id_scheme: IdScheme::Uuid,
+            record_order: false
+        }
+    }
+}
+
+impl<S: Scalar> Rtree<S> {
+    pub fn new(max_children: u8) -> Rtree<S> {
+        Rtree::with_config(RtreeConfig { max_children, ..RtreeConfig::default() })
+    }
+
+    pub fn with_config(config: RtreeConfig) -> Rtree<S> {
+        assert!(config.max_children >= 2, "max_children must be at least 2");
+        if let Some(precision) = config.snap_precision {
+            assert!(
+                precision.is_finite() && precision > 0.0,
+                "snap precision must be finite and positive"
+            );
+        }
+        let origin = (S::zero(), S::zero());
+        let ids = match config.id_scheme {
+            IdScheme::Uuid => IdSource::Uuid,
+            IdScheme::Sequential => IdSource::Sequential(Rc::new(Cell::new(0)))
+        };
+        Rtree {
+            root: RtreeNode::with_ids(
+                BoundingRectangle::new(origin, origin),
+                config.max_children,
+                ChildrenType::Leafs,
+                ids.clone()
+            ),
+            config,
+            len: 0,
+            ids,
+            next_sequence: 1
+        }
+    }
+
+    pub fn with_capacity(max_children: u8, capacity: usize) -> Rtree<S> {
+        let mut tree = Rtree::new(max_children);
+        tree.root.reserve(capacity);
+        tree
+    }
+
+    pub fn config(&self) -> &RtreeConfig {
+        &self.config
+    }
+
+    pub fn root(&self) -> &RtreeNode<S> {
+        &self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, geom: TreeGeometry<S>) -> String {
+        self.insert_entry(geom, None)
+    }
+
+    pub fn insert_coords(&mut self, coords: Geometry<S>) -> Result<String, RtreeError> {
+        if coords.is_empty() {
+            return Err(RtreeError::EmptyGeometry)
+        }
+        Ok(self.insert(RtreeGeometry::new_shared(coords)))
+    }
+
+    pub fn insert_with_id(&mut self, geom: TreeGeometry<S>,
+                          id: String) -> Result<String, RtreeError> {
+        if self.root.contains_id(&id) {
+            return Err(RtreeError::DuplicateId(id))
+        }
+        if let (IdSource::Sequential(next_id), Ok(number)) = (&self.ids, id.parse::<u64>()) {
+            next_id.set(next_id.get().max(number + 1));
+        }
+        Ok(self.insert_entry(geom, Some(id)))
+    }
+
+    fn insert_entry(&mut self, geom: TreeGeometry<S>, id: Option<String>) -> String {
+        if let Some(precision) = self.config.snap_precision {
+            geom.borrow_mut().snap_to(precision);
+        }
+        if self.config.deduplicate {
+            if let Some(existing) = self.find_duplicate(&geom) {
+                return existing
+            }
+        }
+        match id {
+            Some(id) => geom.borrow_mut().id = id,
+            None if self.config.id_scheme == IdScheme::Sequential => {
+                geom.borrow_mut().id = generate_id(&self.ids)
+            },
+            None => ()
+        }
+        if self.config.record_order {
+            geom.borrow_mut().set_sequence(self.next_sequence);
+            self.next_sequence += 1;
+        }
+        let id = geom.borrow().id.clone();
+        self.root.insert(geom);
+        self.len += 1;
+        id
+    }
+
+    pub fn query(&self, rect: BoundingRectangle<S>) -> Vec<Geometry<S>> {
+        self.root.query_window(&rect).into_iter()
+            .filter(|geom| geom.borrow().intersects_rect(&rect))
+            .map(|geom| geom.borrow().coords.clone())
+            .collect()
+    }
+
+    fn find_duplicate(&self, geom: &TreeGeometry<S>) -> Option<String> {
+        let geom = geom.borrow();
+        self.root.query_window(&geom.mbr).into_iter()
+            .find(|candidate| candidate.borrow().coords == geom.coords)
+            .map(|candidate| candidate.borrow().id.clone())
+    }
+
+    pub fn remove(&mut self, id: &str) -> Option<Geometry<S>> {
+        let removed = self.root.delete(id)?;
+        self.len -= 1;
+        let coords = match Rc::try_unwrap(removed) {
+            Ok(geom) => geom.into_inner().coords,
+            Err(geom) => geom.borrow().coords.clone()
+        };
+        Some(coords)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::ControlFlow;
+
+    use super::*;
+    use crate::geometries::GeometryType;
+
+    #[test]
+    fn test_remove() {
+        let mut tree = Rtree::new(4);
+        let mut ids = vec!();
+        for i in 0..10 {
+            let geom = RtreeGeometry::new_shared(vec!((i, i), (i + 1, i + 3)));
+            ids.push(geom.borrow().id.clone());
+            tree.insert(geom);
+        }
+
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.remove(&ids[3]), Some(vec!((3, 3), (4, 6))));
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.remove(&ids[3]), None);
+        assert_eq!(tree.len(), 9);
+        assert_eq!(tree.root().geometries().len(), 9);
+    }
+
+    #[test]
+    fn test_insert_returns_id() {
+        let mut tree = Rtree::new(4);
+        for i in 0..8 {
+            tree.insert(RtreeGeometry::new_shared(vec!((i, 0))));
+        }
+
+        let id = tree.insert(RtreeGeometry::new_shared(vec!((3, 3), (5, 5))));
+        let own_id = tree.insert_with_id(
+            RtreeGeometry::new_shared(vec!((9, 9))),
+            String::from("parcel-9")
+        );
+
+        assert_eq!(own_id, Ok(String::from("parcel-9")));
+        assert_eq!(
+            tree.insert_with_id(RtreeGeometry::new_shared(vec!((7, 7))), String::from("parcel-9")),
+            Err(RtreeError::DuplicateId(String::from("parcel-9")))
+        );
+        assert_eq!(tree.len(), 10);
+        assert_eq!(tree.remove(&id), Some(vec!((3, 3), (5, 5))));
+        assert_eq!(tree.remove("parcel-9"), Some(vec!((9, 9))));
+        assert_eq!(tree.len(), 8);
+    }
+
+    #[test]
+    fn test_deduplicate() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            deduplicate: true,
+            ..RtreeConfig::default()
+        });
+        let mut plain = Rtree::new(4);
+
+        let id = tree.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+        let repeated = tree.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+        let closed = tree.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5), (1, 1))));
+        plain.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+        plain.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+
+        assert_eq!(repeated, id);
+        assert_ne!(closed, id);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.root().geometries().len(), 2);
+        assert_eq!(plain.len(), 2);
+    }
+
+    #[test]
+    fn test_snap_precision() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            snap_precision: Some(0.0001),
+            ..RtreeConfig::default()
+        });
+        let point_1 = RtreeGeometry::new_shared(vec!((37.77491, -122.41942)));
+        let point_2 = RtreeGeometry::new_shared(vec!((37.77489, -122.41938)));
+        let unsnapped = RtreeGeometry::new_shared(vec!((37.77491, -122.41942)));
+
+        tree.insert(point_1.clone());
+        tree.insert(point_2.clone());
+
+        assert_eq!(point_1.borrow().mbr.left, point_2.borrow().mbr.left);
+        assert_eq!(point_1.borrow().mbr.right, point_2.borrow().mbr.right);
+
+        let mut plain = Rtree::new(4);
+        plain.insert(unsnapped.clone());
+
+        assert_eq!(unsnapped.borrow().coords, vec!((37.77491, -122.41942)));
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            max_children: 8,
+            id_scheme: IdScheme::Sequential,
+            ..RtreeConfig::default()
+        });
+        let mut ids = vec!();
+        for i in 0..6 {
+            ids.push(tree.insert(RtreeGeometry::new_shared(vec!((i, i), (i + 2, i + 1)))));
+        }
+
+        assert_eq!(tree.root().id, "0");
+        assert_eq!(ids, vec!("1", "2", "3", "4", "5", "6"));
+
+        tree.remove("6");
+        tree.remove("3");
+        let after_delete = tree.insert(RtreeGeometry::new_shared(vec!((1, 1))));
+        let given = tree.insert_with_id(RtreeGeometry::new_shared(vec!((2, 2))), String::from("10"));
+        let after_given = tree.insert(RtreeGeometry::new_shared(vec!((3, 3))));
+
+        assert_eq!(after_delete, "7");
+        assert_eq!(given, Ok(String::from("10")));
+        assert_eq!(after_given, "11");
+        assert!(tree.root().get("7").is_some());
+        assert_eq!(tree.len(), 7);
+
+        let mut uuids = Rtree::new(4);
+        let id = uuids.insert(RtreeGeometry::new_shared(vec!((1, 1))));
+        assert_eq!(id.len(), 36);
+        assert_eq!(uuids.root().id.len(), 36);
+    }
+
+    #[test]
+    fn test_sequential_node_ids() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            id_scheme: IdScheme::Sequential,
+            ..RtreeConfig::default()
+        });
+        for i in 0..40 {
+            tree.insert(RtreeGeometry::new_shared(vec!((i % 7, i / 7))));
+        }
+
+        let mut ids: Vec<u64> = tree.root().geometries().iter()
+            .map(|geom| geom.borrow().id.parse().unwrap())
+            .collect();
+        tree.root().walk(|node, _| {
+            ids.push(node.id.parse().unwrap());
+            ControlFlow::Continue(())
+        });
+        let total = ids.len();
+        ids.sort_unstable();
+        ids.dedup();
+
+        assert!(total > 41);
+        assert_eq!(ids.len(), total);
+    }
+
+    #[test]
+    fn test_deduplicate_keeps_sequence() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            max_children: 8,
+            deduplicate: true,
+            id_scheme: IdScheme::Sequential,
+            ..RtreeConfig::default()
+        });
+
+        let first = tree.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+        let repeated = tree.insert(RtreeGeometry::new_shared(vec!((1, 1), (4, 5))));
+        let next = tree.insert(RtreeGeometry::new_shared(vec!((2, 2))));
+
+        assert_eq!(first, "1");
+        assert_eq!(repeated, "1");
+        assert_eq!(next, "2");
+    }
+
+    #[test]
+    fn test_with_capacity() {
+        let mut tree: Rtree = Rtree::with_capacity(8, 64);
+
+        assert!(tree.root().children.capacity() >= 64);
+        assert_eq!(tree.len(), 0);
+
+        tree.insert(RtreeGeometry::new_shared(vec!((1, 1))));
+        assert_eq!(tree.len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "snap precision must be finite and positive")]
+    fn test_zero_snap_precision() {
+        let _: Rtree = Rtree::with_config(RtreeConfig {
+            snap_precision: Some(0.0),
+            ..RtreeConfig::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "snap precision must be finite and positive")]
+    fn test_nan_snap_precision() {
+        let _: Rtree<f64> = Rtree::with_config(RtreeConfig {
+            snap_precision: Some(f64::NAN),
+            ..RtreeConfig::default()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "max_children must be at least 2")]
+    fn test_single_max_children() {
+        let _: Rtree = Rtree::with_config(RtreeConfig {
+            max_children: 1,
+            ..RtreeConfig::default()
+        });
+    }
+
+    #[test]
+    fn test_record_order() {
+        let mut tree = Rtree::with_config(RtreeConfig {
+            record_order: true,
+            ..RtreeConfig::default()
+        });
+        let first = RtreeGeometry::new_shared(vec!((9, 9)));
+        let second = RtreeGeometry::new_shared(vec!((1, 1), (2, 2)));
+        let third = RtreeGeometry::new_shared(vec!((5, 5)));
+        tree.insert(first.clone());
+        tree.insert(second.clone());
+        tree.insert(third.clone());
+
+        assert!(first.borrow().sequence() < second.borrow().sequence());
+        assert!(second.borrow().sequence() < third.borrow().sequence());
+
+        let ordered: Vec<String> = tree.root().iter_by_insertion_order().iter()
+            .map(|geom| geom.borrow().id.clone())
+            .collect();
+        assert_eq!(ordered, vec!(
+            first.borrow().id.clone(),
+            second.borrow().id.clone(),
+            third.borrow().id.clone()
+        ));
+
+        let mut plain = Rtree::new(4);
+        let unrecorded = RtreeGeometry::new_shared(vec!((1, 1)));
+        plain.insert(unrecorded.clone());
+        assert_eq!(unrecorded.borrow().sequence(), 0);
+    }
+
+    #[test]
+    fn test_insert_coords() {
+        let mut tree = Rtree::new(4);
+
+        let point = tree.insert_coords(vec!((2, 3))).unwrap();
+        let line = tree.insert_coords(vec!((10, 10), (14, 12))).unwrap();
+        let polygon = tree.insert_coords(vec!((20, 20), (25, 20), (22, 24), (20, 20))).unwrap();
+
+        assert_eq!(tree.insert_coords(vec!()), Err(RtreeError::EmptyGeometry));
+        assert_eq!(tree.len(), 3);
+        assert_eq!(tree.root().get(&point).unwrap().borrow().coordtype, GeometryType::Point);
+        assert_eq!(tree.root().get(&line).unwrap().borrow().coordtype, GeometryType::Line);
+        assert_eq!(tree.root().get(&polygon).unwrap().borrow().coordtype, GeometryType::Polygon);
+
+        let found = tree.root().query_window(&BoundingRectangle::new((11, 11), (21, 21)));
+        let mut found: Vec<String> = found.iter().map(|geom| geom.borrow().id.clone()).collect();
+        let mut expected = vec!(line, polygon);
+        found.sort();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn test_query() {
+        let mut tree = Rtree::new(4);
+        tree.insert_coords(vec!((0, 10), (10, 0))).unwrap();
+        tree.insert_coords(vec!((0, 20), (20, 0))).unwrap();
+        tree.insert_coords(vec!((9, 11))).unwrap();
+        tree.insert_coords(vec!((40, 40), (45, 45))).unwrap();
+
+        let rect = BoundingRectangle::new((8, 8), (12, 12));
+        let mut found = tree.query(rect);
+        found.sort();
+
+        assert_eq!(tree.root().query_window(&rect).len(), 3);
+        assert_eq!(found, vec!(vec!((0, 20), (20, 0)), vec!((9, 11))));
+    }
+}
@@ -1,44 +1,73 @@
-use std::mem::MaybeUninit;
+use std::cell::Cell;
 use std::rc::Rc;
 
 use uuid::Uuid;
 
-use crate::INF;
+use crate::Scalar;
 use crate::geometries::BoundingRectangle;
-use crate::nodes::{RtreeNode, RtreeObject, TreeGeometry, TreeNode};
+use crate::nodes::{RtreeObject, TreeNode};
+#[cfg(test)]
+use crate::geometries::RtreeGeometry;
+#[cfg(test)]
+use crate::nodes::{ChildrenType, RtreeNode};
 
-pub fn generate_id() -> String {
-    Uuid::new_v4().to_hyphenated().to_string()
+#[derive(Clone)]
+pub enum IdSource {
+    Uuid,
+    Sequential(Rc<Cell<u64>>)
 }
 
-pub fn find_least_enlargement<'a>(
-    list_nodes: &'a mut Vec<TreeNode>,
-    mbr: &BoundingRectangle
-) -> (TreeNode, BoundingRectangle) {
+pub fn generate_id(source: &IdSource) -> String {
+    match source {
+        IdSource::Uuid => Uuid::new_v4().to_hyphenated().to_string(),
+        IdSource::Sequential(next_id) => {
+            let id = next_id.get();
+            next_id.set(id + 1);
+            id.to_string()
+        }
+    }
+}
+
+pub fn partial_max<S: Scalar>(a: S, b: S) -> S {
+    if b > a { b } else { a }
+}
+
+pub fn partial_min<S: Scalar>(a: S, b: S) -> S {
+    if b < a { b } else { a }
+}
+
+pub fn find_least_enlargement<S: Scalar>(
+    list_nodes: &mut Vec<TreeNode<S>>,
+    mbr: &BoundingRectangle<S>
+) -> (TreeNode<S>, BoundingRectangle<S>) {
 
-    let mut min_enlargement = INF;
-    let mut min_mbr = MaybeUninit::<BoundingRectangle>::uninit();
-    let mut chosen_node = MaybeUninit::<TreeNode>::uninit();
+    let mut min_enlargement = S::max_value();
+    let mut chosen: Option<(TreeNode<S>, BoundingRectangle<S>)> = None;
 
     for node in list_nodes {
         let node_val = node.borrow();
         let node_mbr = node_val.mbr();
-        let enlarged = BoundingRectangle::common_mbr(
-            &vec!(node_mbr, mbr)
-        );
+        let enlarged = node_mbr.union(mbr);
         let enlargement = enlarged.area - node_mbr.area;
 
-        if enlargement < min_enlargement {
+        if chosen.is_none() || enlargement < min_enlargement {
             min_enlargement = enlargement;
-            unsafe {
-                min_mbr.as_mut_ptr().write(enlarged);
-                chosen_node.as_mut_ptr().write((*node).clone());
-            }
+            chosen = Some(((*node).clone(), enlarged));
         }
     };
-    
-    let min_mbr = unsafe { min_mbr.assume_init() };
-    let chosen_node = unsafe { chosen_node.assume_init() };
 
-    (chosen_node, min_mbr)   
+    chosen.expect("find_least_enlargement needs at least one node")
+}
+
+#[cfg(test)]
+pub fn grid_tree(size: i64) -> RtreeNode {
+    let mut root = RtreeNode::new(
+        BoundingRectangle::new((0, 0), (0, 0)), 8, ChildrenType::Leafs
+    );
+    for x in 0..size {
+        for y in 0..size {
+            root.insert(RtreeGeometry::new_shared(vec!((x * 3, y * 3), (x * 3 + 2, y * 3 + 1))));
+        }
+    }
+    root
 }